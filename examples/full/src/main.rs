@@ -1,4 +1,4 @@
-#![allow(unused_imports)]
+#![allow(unused_imports, clippy::unnecessary_operation)]
 
 #[macro_use]
 extern crate log;
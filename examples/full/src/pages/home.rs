@@ -1,6 +1,6 @@
 use std::{cell::RefCell, rc::Rc};
 
-use editor::{load_and_register, ListenerEvent, ListenerId, MouseListener, SaveState};
+use editor::{load_and_register, EditorEvent, ListenerEvent, ListenerId, MouseListener, SaveState};
 use wasm_bindgen::UnwrapThrowExt;
 use web_sys::{window, HtmlElement};
 use yew::{
@@ -33,13 +33,20 @@ pub fn home() -> Html {
                 node.cast::<HtmlElement>().unwrap_throw(),
                 MouseListener::All,
                 None,
-                Some(Rc::new(RefCell::new(move |id: ListenerId| {
-                    let save = id.try_save();
+                Some(
+                    Rc::new(RefCell::new(move |id: ListenerId, event: &EditorEvent| {
+                        if event != &EditorEvent::Changed {
+                            return;
+                        }
+
+                        let save = id.try_save();
 
-                    debug.set(format!("{save:#?}"));
+                        debug.set(format!("{save:#?}"));
 
-                    *last_save.borrow_mut() = save;
-                })) as ListenerEvent),
+                        *last_save.borrow_mut() = save;
+                    })) as ListenerEvent,
+                ),
+                None,
             )
             .expect_throw("Registering");
 
@@ -90,13 +97,20 @@ pub fn home() -> Html {
                         v.clone(),
                         MouseListener::All,
                         None,
-                        Some(Rc::new(RefCell::new(move |id: ListenerId| {
-                            let save = id.try_save();
+                        Some(
+                            Rc::new(RefCell::new(move |id: ListenerId, event: &EditorEvent| {
+                                if event != &EditorEvent::Changed {
+                                    return;
+                                }
+
+                                let save = id.try_save();
 
-                            debug.set(format!("{save:#?}"));
+                                debug.set(format!("{save:#?}"));
 
-                            *last_save2.borrow_mut() = save;
-                        })) as ListenerEvent),
+                                *last_save2.borrow_mut() = save;
+                            })) as ListenerEvent,
+                        ),
+                        None,
                     ) {
                         Ok(v) => v,
                         Err(e) => {
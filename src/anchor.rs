@@ -0,0 +1,323 @@
+//! Text anchors modeled after the W3C Web Annotation selectors.
+//!
+//! All offsets are in UTF-16 code units to match the DOM.
+
+use std::{cmp::Reverse, ops::Range};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "frontend")]
+use web_sys::{Element, Node, XPathResult};
+
+#[cfg(feature = "frontend")]
+use crate::Result;
+
+/// Amount of code units stored before and after the quote.
+const CONTEXT_LENGTH: usize = 32;
+
+/// How far away from the saved position we'll look for an approximate match.
+const FUZZY_SEARCH_DISTANCE: usize = 2048;
+
+/// Locates a piece of text again after the document has changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextAnchor {
+    pub quote: TextQuoteSelector,
+    pub position: TextPositionSelector,
+    /// Element the text was inside of. Narrows down the search.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerHint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextQuoteSelector {
+    pub exact: String,
+    pub prefix: String,
+    pub suffix: String,
+}
+
+/// Offsets into the text of every Text Node inside the listener Element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextPositionSelector {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// Selector relative to the listener Element.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerHint {
+    Css(String),
+    XPath(String),
+}
+
+impl TextAnchor {
+    pub fn new(text: &[u16], range: Range<usize>, container: Option<ContainerHint>) -> Self {
+        let prefix_start = range.start.saturating_sub(CONTEXT_LENGTH);
+        let suffix_end = text.len().min(range.end + CONTEXT_LENGTH);
+
+        Self {
+            quote: TextQuoteSelector {
+                exact: String::from_utf16_lossy(&text[range.clone()]),
+                prefix: String::from_utf16_lossy(&text[prefix_start..range.start]),
+                suffix: String::from_utf16_lossy(&text[range.end..suffix_end]),
+            },
+            position: TextPositionSelector {
+                start: range.start as u32,
+                end: range.end as u32,
+            },
+            container,
+        }
+    }
+
+    /// Finds the anchored text inside `text`, optionally only searching inside `within`.
+    ///
+    /// Tries the saved position first, then every exact quote match and lastly an approximate match.
+    pub fn locate(&self, text: &[u16], within: Option<Range<usize>>) -> Option<Range<usize>> {
+        let exact = self.quote.exact.encode_utf16().collect::<Vec<_>>();
+
+        if exact.is_empty() {
+            return None;
+        }
+
+        let bounds = within
+            .clone()
+            .map(|v| v.start.min(text.len())..v.end.min(text.len()))
+            .unwrap_or(0..text.len());
+
+        let position = self.position.start as usize..self.position.end as usize;
+
+        if position.start >= bounds.start
+            && position.end <= bounds.end
+            && text.get(position.clone()) == Some(&exact[..])
+        {
+            return Some(position);
+        }
+
+        let prefix = self.quote.prefix.encode_utf16().collect::<Vec<_>>();
+        let suffix = self.quote.suffix.encode_utf16().collect::<Vec<_>>();
+
+        let best = find_all(&text[bounds.clone()], &exact)
+            .map(|start| start + bounds.start)
+            .max_by_key(|&start| {
+                (
+                    context_score(text, start..start + exact.len(), &prefix, &suffix),
+                    Reverse(start.abs_diff(position.start)),
+                )
+            });
+
+        if let Some(start) = best {
+            return Some(start..start + exact.len());
+        }
+
+        // Only look around the saved position unless we know which Element it's in.
+        let window = if within.is_some() {
+            bounds
+        } else {
+            bounds
+                .start
+                .max(position.start.saturating_sub(FUZZY_SEARCH_DISTANCE))
+                ..bounds
+                    .end
+                    .min(position.start + exact.len() + FUZZY_SEARCH_DISTANCE)
+        };
+
+        if window.is_empty() {
+            return None;
+        }
+
+        fuzzy_find(&text[window.clone()], &exact, exact.len() / 4)
+            .map(|found| found.start + window.start..found.end + window.start)
+    }
+}
+
+fn find_all<'a>(text: &'a [u16], pattern: &'a [u16]) -> impl Iterator<Item = usize> + 'a {
+    text.windows(pattern.len())
+        .enumerate()
+        .filter_map(move |(i, v)| (v == pattern).then_some(i))
+}
+
+/// Amount of code units which match the prefix and suffix surrounding the range.
+fn context_score(text: &[u16], range: Range<usize>, prefix: &[u16], suffix: &[u16]) -> usize {
+    let before = text[..range.start]
+        .iter()
+        .rev()
+        .zip(prefix.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let after = text[range.end..]
+        .iter()
+        .zip(suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    before + after
+}
+
+/// Finds the substring of `text` with the lowest edit distance to `pattern`.
+///
+/// Uses Sellers' algorithm, keeping track of where each match started.
+fn fuzzy_find(text: &[u16], pattern: &[u16], max_errors: usize) -> Option<Range<usize>> {
+    // (edit distance, start position) for each pattern prefix length.
+    let mut prev = (0..=pattern.len()).map(|i| (i, 0)).collect::<Vec<_>>();
+    let mut best: Option<(usize, Range<usize>)> = None;
+
+    for (j, &unit) in text.iter().enumerate() {
+        let mut curr = vec![(0, j + 1); pattern.len() + 1];
+
+        for i in 1..=pattern.len() {
+            let substitute = (
+                prev[i - 1].0 + usize::from(pattern[i - 1] != unit),
+                prev[i - 1].1,
+            );
+            let skip_text = (prev[i].0 + 1, prev[i].1);
+            let skip_pattern = (curr[i - 1].0 + 1, curr[i - 1].1);
+
+            curr[i] = [substitute, skip_text, skip_pattern]
+                .into_iter()
+                .min_by_key(|v| v.0)
+                .unwrap();
+        }
+
+        let (cost, start) = curr[pattern.len()];
+
+        if cost <= max_errors && best.as_ref().is_none_or(|(best, _)| cost < *best) {
+            best = Some((cost, start..j + 1));
+        }
+
+        prev = curr;
+    }
+
+    best.map(|(_, range)| range)
+}
+
+#[cfg(feature = "frontend")]
+/// Creates a CSS selector from the `container` to the `element`.
+///
+/// Our own wrapping spans are skipped since they won't exist when the page is loaded again.
+pub fn css_path(container: &Element, element: &Element) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut current = element.clone();
+
+    while &current != container {
+        let mut index = 1;
+        let mut sibling = current.previous_element_sibling();
+
+        while let Some(value) = sibling {
+            if !is_wrapper(&value) {
+                index += 1;
+            }
+
+            sibling = value.previous_element_sibling();
+        }
+
+        if !is_wrapper(&current) {
+            parts.push(format!(
+                "{}:nth-child({index})",
+                current.tag_name().to_lowercase()
+            ));
+        }
+
+        current = current.parent_element()?;
+    }
+
+    parts.push(String::from(":scope"));
+    parts.reverse();
+
+    Some(parts.join(" > "))
+}
+
+#[cfg(feature = "frontend")]
+fn is_wrapper(element: &Element) -> bool {
+    element.tag_name() == "SPAN" && element.class_name().starts_with("editor-")
+}
+
+#[cfg(feature = "frontend")]
+/// Returns the Element the hint points to.
+pub fn resolve_hint(container: &Element, hint: &ContainerHint) -> Result<Option<Node>> {
+    match hint {
+        ContainerHint::Css(selector) => Ok(container.query_selector(selector)?.map(Into::into)),
+        ContainerHint::XPath(path) => {
+            let Some(document) = container.owner_document() else {
+                warn!("Unable to resolve XPath hint: Container has no document");
+                return Ok(None);
+            };
+
+            document
+                .evaluate_with_opt_callback_and_type(
+                    path,
+                    container,
+                    None,
+                    XPathResult::FIRST_ORDERED_NODE_TYPE,
+                )?
+                .single_node_value()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(value: &str) -> Vec<u16> {
+        value.encode_utf16().collect()
+    }
+
+    #[test]
+    fn locate_at_position() {
+        let text = utf16("The quick brown fox");
+        let anchor = TextAnchor::new(&text, 4..9, None);
+
+        assert_eq!(anchor.locate(&text, None), Some(4..9));
+    }
+
+    #[test]
+    fn locate_moved_text() {
+        let text = utf16("The quick brown fox");
+        let anchor = TextAnchor::new(&text, 10..15, None);
+
+        let text = utf16("Once upon a time. The quick brown fox");
+
+        assert_eq!(anchor.locate(&text, None), Some(28..33));
+    }
+
+    #[test]
+    fn locate_uses_context() {
+        let text = utf16("one cat. two cat.");
+        let anchor = TextAnchor::new(&text, 13..16, None);
+
+        let text = utf16("one cat. zzzzzzzzz two cat.");
+
+        // The first "cat" is closer to the saved position but the second matches the prefix.
+        assert_eq!(anchor.locate(&text, None), Some(23..26));
+        assert_eq!(anchor.locate(&text, Some(0..8)), Some(4..7));
+    }
+
+    #[test]
+    fn locate_fuzzy() {
+        let text = utf16("It was the best of times, it was the worst of times");
+        let anchor = TextAnchor::new(&text, 26..51, None);
+
+        let text = utf16("It was the best of times, it was thee worst of tymes");
+
+        assert_eq!(anchor.locate(&text, None), Some(26..52));
+    }
+
+    #[test]
+    fn locate_missing() {
+        let text = utf16("The quick brown fox");
+        let anchor = TextAnchor::new(&text, 4..9, None);
+
+        assert_eq!(anchor.locate(&utf16("Lorem ipsum dolor"), None), None);
+    }
+
+    #[test]
+    fn locate_utf16_offsets() {
+        let text = utf16("“Quoted” 🦀 crab");
+        let anchor = TextAnchor::new(&text, 12..16, None);
+
+        assert_eq!(anchor.quote.exact, "crab");
+
+        let text = utf16("🦀🦀 “Quoted” 🦀 crab");
+
+        assert_eq!(anchor.locate(&text, None), Some(17..21));
+    }
+}
@@ -0,0 +1,26 @@
+use crate::ComponentFlag;
+#[cfg(feature = "frontend")]
+use crate::Result;
+
+use super::Component;
+#[cfg(feature = "frontend")]
+use super::Context;
+
+pub struct Bold;
+
+impl Component for Bold {
+    const FLAG: ComponentFlag = ComponentFlag::BOLD;
+    const TITLE: &'static str = "Bold";
+    const CLASS_NAME: Option<&'static str> = Some("editor-bold");
+
+    type Data = ();
+
+    #[cfg(feature = "frontend")]
+    fn on_click_button(&self, ctx: &Context<Self>) -> Result<()> {
+        debug!("Bold");
+
+        ctx.nodes.borrow_mut().toggle_selection::<Self>()?;
+
+        Ok(())
+    }
+}
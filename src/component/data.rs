@@ -0,0 +1,321 @@
+use bitflags::bitflags;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::store::DecodeError;
+
+/// Stable id of a [`ComponentDataStore`].
+///
+/// Unlike the data index it doesn't change when other data is removed.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct AnnotationId(pub(crate) u64);
+
+impl AnnotationId {
+    /// Data which wasn't stored in a listener yet.
+    pub(crate) const UNSET: Self = Self(0);
+
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    pub fn is_unset(self) -> bool {
+        self == Self::UNSET
+    }
+}
+
+impl From<u64> for AnnotationId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl std::fmt::Display for AnnotationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// `ComponentFlag` is used to determine the type of component the Store is for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "DataStoreRepr")]
+pub struct ComponentDataStore(
+    pub(crate) ComponentFlag,
+    pub(crate) String,
+    pub(crate) AnnotationId,
+);
+
+/// Stores saved before the ids were added only contain the flag and the value.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DataStoreRepr {
+    WithId(ComponentFlag, String, AnnotationId),
+    WithoutId(ComponentFlag, String),
+}
+
+impl From<DataStoreRepr> for ComponentDataStore {
+    fn from(value: DataStoreRepr) -> Self {
+        match value {
+            DataStoreRepr::WithId(flag, value, id) => Self(flag, value, id),
+            DataStoreRepr::WithoutId(flag, value) => Self(flag, value, AnnotationId::UNSET),
+        }
+    }
+}
+
+impl ComponentDataStore {
+    /// The id is assigned once the data is stored in a listener.
+    pub fn new<S: Serialize>(flag: ComponentFlag, value: &S) -> Self {
+        Self(
+            flag,
+            serde_json::to_string(value).unwrap(),
+            AnnotationId::UNSET,
+        )
+    }
+
+    pub fn id(&self) -> AnnotationId {
+        self.2
+    }
+
+    pub fn update<S: Serialize>(&mut self, value: &S) {
+        self.1 = serde_json::to_string(value).unwrap();
+    }
+
+    pub fn parse<D: DeserializeOwned>(&self) -> D {
+        serde_json::from_str(&self.1).unwrap()
+    }
+
+    pub fn try_parse<D: DeserializeOwned>(&self) -> serde_json::Result<D> {
+        serde_json::from_str(&self.1)
+    }
+
+    pub fn flag(&self) -> ComponentFlag {
+        self.0
+    }
+}
+
+bitflags! {
+    #[derive(Default, Serialize, Deserialize)]
+    pub struct ComponentFlag: u32 {
+        const ITALICIZE = 0b0000_0001;
+        const HIGHLIGHT = 0b0000_0010;
+        const UNDERLINE = 0b0000_0100;
+        const NOTE      = 0b0000_1000;
+        const LIST      = 0b0001_0000;
+        const BOLD      = 0b0010_0000;
+
+        /// Bits reserved for custom Components.
+        const CUSTOM    = 0xFFFF_0000;
+    }
+}
+
+impl ComponentFlag {
+    /// Flag for a custom Component. The index has to be less than 16.
+    pub const fn custom(index: u32) -> Self {
+        assert!(index < 16, "Custom Component index out of range");
+
+        Self::from_bits_truncate(1 << (16 + index))
+    }
+
+    /// Splits the flag into its' single bits.
+    pub fn separate_bits(self) -> Vec<Self> {
+        (0..u32::BITS)
+            .map(|bit| 1 << bit)
+            .filter(|&bit| self.bits() & bit != 0)
+            .map(Self::from_bits_truncate)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlagsWithData {
+    pub flag: ComponentFlag,
+    /// Singular Flag with data.
+    pub data: Vec<(ComponentFlag, u32)>,
+}
+
+impl FlagsWithData {
+    pub fn new_flag(flag: ComponentFlag) -> Self {
+        Self {
+            flag,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn new_with_data(flag: ComponentFlag, data: u32) -> Self {
+        Self {
+            flag,
+            data: vec![(flag, data)],
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            flag: ComponentFlag::empty(),
+            data: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flag.is_empty()
+    }
+
+    /// Returns true if there are flags common to both.
+    pub fn intersects_flag(&self, value: ComponentFlag) -> bool {
+        self.flag.intersects(value)
+    }
+
+    // TODO: Rename to Contains Flag
+    pub fn contains(&self, value: &Self) -> bool {
+        if self.flag.contains(value.flag) {
+            for a in &value.data {
+                if !self.data.iter().any(|b| b == a) {
+                    return false;
+                }
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Adds the flags and data. A flag can contain multiple data ids.
+    pub fn insert(&mut self, value: Self) {
+        self.flag.insert(value.flag);
+
+        for item in value.data {
+            if !self.data.contains(&item) {
+                self.data.push(item);
+            }
+        }
+
+        self.data.sort_unstable();
+    }
+
+    pub fn clear(&mut self) {
+        self.flag = ComponentFlag::empty();
+        self.data.clear();
+    }
+
+    /// Removes the data ids of `value`. Flags without any data ids in `value` are removed entirely.
+    ///
+    /// A flag is kept while it still contains other data ids.
+    pub fn remove(&mut self, value: &Self) {
+        for flag in value.flag.separate_bits() {
+            if value.data.iter().any(|v| v.0 == flag) {
+                self.data.retain(|v| v.0 != flag || !value.data.contains(v));
+            } else {
+                self.data.retain(|v| v.0 != flag);
+            }
+
+            if !self.data.iter().any(|v| v.0 == flag) {
+                self.flag.remove(flag);
+            }
+        }
+    }
+
+    /// Returns the data ids of the flag.
+    pub fn data_of(&self, flag: ComponentFlag) -> impl Iterator<Item = u32> + '_ {
+        self.data
+            .iter()
+            .filter(move |v| v.0 == flag)
+            .map(|&(_, data)| data)
+    }
+
+    /// One item for every data id. Flags without data use 0.
+    pub fn into_singles_vec(&self) -> Vec<SingleFlagWithData> {
+        self.flag
+            .separate_bits()
+            .into_iter()
+            .flat_map(|flag| {
+                let mut data = self.data_of(flag).collect::<Vec<_>>();
+
+                if data.is_empty() {
+                    data.push(0);
+                }
+
+                data.into_iter()
+                    .map(move |data| SingleFlagWithData::new(flag, data))
+            })
+            .collect()
+    }
+
+    pub fn from_singles(value: &[SingleFlagWithData]) -> Self {
+        let mut this = Self::empty();
+
+        for item in value {
+            this.flag.insert(item.flag());
+            this.data.push((item.flag(), item.data()));
+        }
+
+        this.data.sort_unstable();
+
+        this
+    }
+}
+
+/// A single flag along with one of its' data ids.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "SingleFlagRepr", into = "SingleFlagRepr")]
+pub struct SingleFlagWithData {
+    flag: ComponentFlag,
+    data: u32,
+}
+
+impl SingleFlagWithData {
+    pub fn new(flag: ComponentFlag, data: u32) -> Self {
+        Self { flag, data }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(ComponentFlag::empty(), 0)
+    }
+
+    pub fn flag(self) -> ComponentFlag {
+        self.flag
+    }
+
+    pub fn data(self) -> u32 {
+        self.data
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SingleFlagRepr {
+    /// Flag bits and data id.
+    Pair(u32, u32),
+    /// Older states packed the flag bits into the upper and the data id into the lower 32 bits.
+    Packed(u64),
+}
+
+impl TryFrom<SingleFlagRepr> for SingleFlagWithData {
+    type Error = DecodeError;
+
+    fn try_from(value: SingleFlagRepr) -> Result<Self, Self::Error> {
+        let (bits, data) = match value {
+            SingleFlagRepr::Pair(bits, data) => (bits, data),
+            SingleFlagRepr::Packed(value) => ((value >> 32) as u32, value as u32),
+        };
+
+        let flag = ComponentFlag::from_bits(bits).ok_or(DecodeError::UnknownFlag(bits))?;
+
+        Ok(Self::new(flag, data))
+    }
+}
+
+impl From<SingleFlagWithData> for SingleFlagRepr {
+    fn from(value: SingleFlagWithData) -> Self {
+        Self::Pair(value.flag.bits(), value.data)
+    }
+}
+
+impl std::fmt::Debug for SingleFlagWithData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SingleFlagWithData")
+            .field(&self.flag())
+            .field(&self.data())
+            .finish()
+    }
+}
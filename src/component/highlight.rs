@@ -1,19 +1,29 @@
 use std::borrow::Cow;
 
-use num_enum::{IntoPrimitive, TryFromPrimitive};
+use serde::{Deserialize, Serialize};
 
-use crate::{ComponentFlag, Result};
+#[cfg(feature = "frontend")]
+use crate::Result;
+use crate::{helper::is_hex_color, ComponentFlag};
 
-use super::{Component, ComponentData, Context};
+use super::{Component, ComponentData, ComponentDataStore};
+#[cfg(feature = "frontend")]
+use super::{Context, ExpandedOption, FlagsWithData};
+
+/// Data ids at or above this point refer to a [`HighlightColor`].
+const CUSTOM_COLOR_OFFSET: u32 = 0x80;
 
 pub struct Highlight;
 
 impl Component for Highlight {
     const FLAG: ComponentFlag = ComponentFlag::HIGHLIGHT;
     const TITLE: &'static str = "Highlight";
+    const CLASS_NAME: Option<&'static str> = Some("editor-highlight");
+    const OVERLAPPING: bool = true;
 
     type Data = HighlightTypes;
 
+    #[cfg(feature = "frontend")]
     fn on_click_button(&self, ctx: &Context<Self>) -> Result<()> {
         debug!("Highlight");
 
@@ -21,31 +31,129 @@ impl Component for Highlight {
 
         Ok(())
     }
+
+    #[cfg(feature = "frontend")]
+    fn expanded_options(&self, ctx: &Context<Self>) -> Vec<ExpandedOption> {
+        let built_in = HighlightTypes::ALL.iter().map(|v| ExpandedOption {
+            title: Cow::Borrowed(v.title()),
+            data: v.id(),
+        });
+
+        let custom = ctx.get_all_data().into_iter().filter_map(|(index, store)| {
+            let color = store.try_parse::<HighlightColor>().ok()?;
+
+            Some(ExpandedOption {
+                title: Cow::Owned(color.name),
+                data: Self::data_id_for_index(index),
+            })
+        });
+
+        built_in.chain(custom).collect()
+    }
+
+    #[cfg(feature = "frontend")]
+    fn on_click_option(&self, ctx: &Context<Self>, data: u32) -> Result<()> {
+        debug!("Highlight - Color {data}");
+
+        // Each color is toggled on its' own. Different colors can overlap.
+        let flag = FlagsWithData::new_with_data(Self::FLAG, data);
+
+        if ctx.nodes.borrow().does_selected_contain(&flag) {
+            ctx.remove_selection(Some(data))?;
+        } else if let Err(e) = ctx.insert_selection(Some(data))? {
+            error!("{e}");
+        }
+
+        Ok(())
+    }
+
+    fn stylesheet(&self, data: &[ComponentDataStore]) -> Option<String> {
+        let rules = data
+            .iter()
+            .enumerate()
+            .filter(|(_, store)| store.flag() == Self::FLAG)
+            .filter_map(|(index, store)| {
+                let color = store.try_parse::<HighlightColor>().ok()?;
+
+                if !color.is_valid() {
+                    warn!("Invalid Highlight Color {:?}", color.color);
+                    return None;
+                }
+
+                Some(format!(
+                    ".editor-highlight.{} {{ background-color: {} !important; }}",
+                    HighlightTypes::Custom(index as u32).css(),
+                    color.color
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        (!rules.is_empty()).then(|| rules.join("\n"))
+    }
+
+    fn data_id_for_index(index: u32) -> u32 {
+        HighlightTypes::Custom(index).id()
+    }
 }
 
-#[derive(Clone, Copy, TryFromPrimitive, IntoPrimitive)]
-#[repr(u8)]
+/// A user defined Highlight color. Stored as the Highlights' Component data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighlightColor {
+    pub name: String,
+    /// Hex color. Either `#rgb` or `#rrggbb`.
+    pub color: String,
+}
+
+impl HighlightColor {
+    pub fn new(name: impl Into<String>, color: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            color: color.into(),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        is_hex_color(&self.color)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HighlightTypes {
     Yellow,
     Orange,
     Blue,
     Purple,
+    /// Index of the [`HighlightColor`] in the listeners' data.
+    Custom(u32),
 }
 
 impl HighlightTypes {
-    pub fn css(self) -> &'static str {
+    pub const ALL: [Self; 4] = [Self::Yellow, Self::Orange, Self::Blue, Self::Purple];
+
+    pub fn title(self) -> &'static str {
         match self {
-            Self::Yellow => "yellow",
-            Self::Orange => "orange",
-            Self::Blue => "blue",
-            Self::Purple => "purple",
+            Self::Yellow => "Yellow",
+            Self::Orange => "Orange",
+            Self::Blue => "Blue",
+            Self::Purple => "Purple",
+            Self::Custom(_) => "Custom",
+        }
+    }
+
+    pub fn css(self) -> Cow<'static, str> {
+        match self {
+            Self::Yellow => Cow::Borrowed("yellow"),
+            Self::Orange => Cow::Borrowed("orange"),
+            Self::Blue => Cow::Borrowed("blue"),
+            Self::Purple => Cow::Borrowed("purple"),
+            Self::Custom(index) => Cow::Owned(format!("editor-color-{index}")),
         }
     }
 }
 
 impl ComponentData for HighlightTypes {
     fn get_css(&self) -> Option<Cow<'static, str>> {
-        Some(Cow::Borrowed(self.css()))
+        Some(self.css())
     }
 
     fn default() -> Option<Self> {
@@ -53,10 +161,66 @@ impl ComponentData for HighlightTypes {
     }
 
     fn id(&self) -> u32 {
-        *self as u32
+        match *self {
+            Self::Yellow => 0,
+            Self::Orange => 1,
+            Self::Blue => 2,
+            Self::Purple => 3,
+            Self::Custom(index) => CUSTOM_COLOR_OFFSET + index,
+        }
     }
 
     fn from_id(value: u32) -> Self {
-        HighlightTypes::try_from_primitive(value as u8).unwrap()
+        match value {
+            0 => Self::Yellow,
+            1 => Self::Orange,
+            2 => Self::Blue,
+            3 => Self::Purple,
+            v if v >= CUSTOM_COLOR_OFFSET => Self::Custom(v - CUSTOM_COLOR_OFFSET),
+            v => {
+                warn!("Unknown Highlight Color {v}");
+                Self::Yellow
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_ids() {
+        for value in HighlightTypes::ALL
+            .into_iter()
+            .chain([HighlightTypes::Custom(0), HighlightTypes::Custom(5)])
+        {
+            assert_eq!(HighlightTypes::from_id(value.id()), value);
+        }
+
+        // Unknown ids fall back to the default instead of panicking.
+        assert_eq!(HighlightTypes::from_id(42), HighlightTypes::Yellow);
+    }
+
+    #[test]
+    fn custom_color_stylesheet() {
+        let data = [
+            ComponentDataStore::new(ComponentFlag::NOTE, &"note"),
+            ComponentDataStore::new(
+                ComponentFlag::HIGHLIGHT,
+                &HighlightColor::new("Mint", "#98ff98"),
+            ),
+            ComponentDataStore::new(
+                ComponentFlag::HIGHLIGHT,
+                &HighlightColor::new("Bad", "red; } body { display: none"),
+            ),
+        ];
+
+        assert_eq!(
+            Highlight.stylesheet(&data).as_deref(),
+            Some(".editor-highlight.editor-color-1 { background-color: #98ff98 !important; }")
+        );
+
+        assert_eq!(Highlight.stylesheet(&data[..1]), None);
     }
 }
@@ -1,15 +1,21 @@
-use crate::{ComponentFlag, Result};
+use crate::ComponentFlag;
+#[cfg(feature = "frontend")]
+use crate::Result;
 
-use super::{Component, Context};
+use super::Component;
+#[cfg(feature = "frontend")]
+use super::Context;
 
 pub struct Italicize;
 
 impl Component for Italicize {
     const FLAG: ComponentFlag = ComponentFlag::ITALICIZE;
     const TITLE: &'static str = "Italicize";
+    const CLASS_NAME: Option<&'static str> = Some("editor-italicize");
 
     type Data = ();
 
+    #[cfg(feature = "frontend")]
     fn on_click_button(&self, ctx: &Context<Self>) -> Result<()> {
         debug!("Italicize");
 
@@ -1,12 +1,26 @@
-use std::cell::RefCell;
+#[cfg(feature = "frontend")]
+use std::{cell::RefCell, rc::Rc};
 
-use gloo_utils::body;
+#[cfg(feature = "frontend")]
+use gloo_utils::{body, window};
+#[cfg(feature = "frontend")]
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue, UnwrapThrowExt};
-use web_sys::{Element, HtmlElement};
-
-use crate::{ComponentFlag, Result};
-
-use super::{Component, Context};
+#[cfg(feature = "frontend")]
+use web_sys::{Element, HtmlElement, MouseEvent, Range};
+
+use crate::ComponentFlag;
+#[cfg(feature = "frontend")]
+use crate::{
+    event::{Annotation, EditorEvent, ListenerEvent, SubscriptionId},
+    helper::TargetCast,
+    selection::create_container,
+    util::ElementEvent,
+    ListenerId, Result, SharedListenerData,
+};
+
+use super::Component;
+#[cfg(feature = "frontend")]
+use super::{Context, Note};
 
 pub struct List;
 
@@ -17,6 +31,7 @@ impl Component for List {
 
     type Data = ();
 
+    #[cfg(feature = "frontend")]
     fn on_click_button(&self, ctx: &Context<Self>) -> Result<()> {
         debug!("List");
 
@@ -26,32 +41,78 @@ impl Component for List {
     }
 }
 
+#[cfg(feature = "frontend")]
 thread_local! {
     static DISPLAYING: RefCell<Option<Popup>> = RefCell::default();
 }
 
+#[cfg(feature = "frontend")]
 #[allow(dead_code)]
 struct Popup {
+    events: Vec<ElementEvent>,
+
+    listener_id: ListenerId,
+    /// Refreshes the items after every change.
+    subscription: Option<SubscriptionId>,
+
     content: Element,
-    cancel_fn: Closure<dyn FnMut()>,
 }
 
+#[cfg(feature = "frontend")]
 impl Popup {
     pub fn close(self) {
+        if let Some(id) = self.subscription {
+            if let Some(listener) = self.listener_id.try_get() {
+                listener.borrow_mut().subscribers.remove(id);
+            }
+        }
+
         self.content.remove();
     }
 }
 
-fn show_popup(ctx: Context<List>) -> Result<(), JsValue> {
-    let cancel_fn = Closure::once(|| {
-        DISPLAYING.with(|popup| {
-            popup.take().unwrap_throw().close();
-        });
+#[cfg(feature = "frontend")]
+fn close_popup() {
+    DISPLAYING.with(|popup| {
+        if let Some(popup) = popup.take() {
+            popup.close();
+        }
     });
+}
+
+#[cfg(feature = "frontend")]
+fn show_popup(ctx: Context<List>) -> Result<(), JsValue> {
+    // Only one list at a time.
+    close_popup();
+
+    let data = ctx.nodes.borrow().data.clone();
+    let listener_id = data
+        .upgrade()
+        .expect_throw("data upgrade")
+        .borrow()
+        .listener_id;
+
+    let close_fn = || {
+        Closure::new(|e: MouseEvent| {
+            let target = e.target_unchecked_into::<HtmlElement>();
+
+            if target.class_list().contains("modal") || target.class_list().contains("btn-close") {
+                close_popup();
+            }
+        }) as Closure<dyn FnMut(MouseEvent)>
+    };
+
+    let mut element_events = Vec::new();
 
     let modal = ctx.document.create_element("div")?;
     modal.class_list().add_2("modal", "d-block")?;
     modal.set_attribute("tabindex", "-1")?;
+    element_events.push(ElementEvent::link(
+        modal.clone().unchecked_into(),
+        close_fn(),
+        |t, f| t.add_event_listener_with_callback("click", f),
+        Box::new(|t, f| t.remove_event_listener_with_callback("click", f)),
+    ));
 
     let content = ctx.document.create_element("div")?;
     content.class_list().add_1("modal-dialog")?;
@@ -78,79 +139,83 @@ fn show_popup(ctx: Context<List>) -> Result<(), JsValue> {
         cancel.class_list().add_1("btn-close")?;
 
         header.append_child(&cancel)?;
-        cancel.add_event_listener_with_callback("click", cancel_fn.as_ref().unchecked_ref())?;
     }
 
+    let items = ctx.document.create_element("div")?;
+
     {
         let body = ctx.document.create_element("div")?;
         body.class_list().add_1("modal-body")?;
         inner.append_child(&body)?;
 
-        let notes_and_highlights = ctx.document.create_element("div")?;
-        body.append_child(&notes_and_highlights)?;
-
-        // Notes and Highlights (X)
-
-        {
-            let nodes = ctx.nodes.borrow();
-            let data = nodes.data.upgrade().unwrap();
-            let data = data.borrow();
-
-            for text_cont in data.get_flagged_text() {
-                let flagged_container: HtmlElement =
-                    ctx.document.create_element("div")?.unchecked_into();
-                flagged_container
-                    .class_list()
-                    .add_1("editor-flagged-item")?;
-
-                // TODO: Allow for handling custom data.
-
-                let content: HtmlElement = ctx.document.create_element("p")?.unchecked_into();
-                content.class_list().add_1("editor-content")?;
-                content.set_inner_text(&text_cont.content);
-                flagged_container.append_child(&content)?;
-
-                let footer = ctx.document.create_element("div")?;
-                footer.class_list().add_1("editor-footer")?;
-                flagged_container.append_child(&footer)?;
-
-                match text_cont.flag {
-                    ComponentFlag::HIGHLIGHT => {
-                        let remove: HtmlElement =
-                            ctx.document.create_element("span")?.unchecked_into();
-                        remove.class_list().add_1("editor-clickable")?;
-                        remove.set_inner_text("Remove");
-                        footer.append_child(&remove)?;
-                    }
+        body.append_child(&items)?;
+    }
 
-                    ComponentFlag::NOTE => {
-                        let remove: HtmlElement =
-                            ctx.document.create_element("span")?.unchecked_into();
-                        remove.class_list().add_1("editor-clickable")?;
-                        remove.set_inner_text("Remove");
-                        footer.append_child(&remove)?;
-
-                        let edit: HtmlElement =
-                            ctx.document.create_element("span")?.unchecked_into();
-                        edit.class_list().add_1("editor-clickable")?;
-                        edit.set_inner_text("Edit");
-                        footer.append_child(&edit)?;
-                    }
+    // The annotations of the rendered items. Indexed by the items' `data-index`.
+    let annotations = Rc::new(RefCell::new(Vec::new()));
+
+    render_items(&ctx, &data, &items, &annotations)?;
 
-                    _ => (),
+    // Handles the clicks of every item.
+    {
+        let ctx = ctx.clone();
+        let data = data.clone();
+        let annotations = annotations.clone();
+
+        element_events.push(ElementEvent::link(
+            items.clone().unchecked_into(),
+            Closure::new(move |e: MouseEvent| {
+                let target: Element = e.target_unchecked_into();
+
+                let Some(item) = target.closest(".editor-flagged-item").ok().flatten() else {
+                    return;
+                };
+
+                let Some(annotation) = item
+                    .get_attribute("data-index")
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .and_then(|index| annotations.borrow().get(index).cloned())
+                else {
+                    return;
+                };
+
+                match target.get_attribute("data-action").as_deref() {
+                    Some("remove") => remove_annotation(&ctx, &data, &annotation),
+                    Some("edit") => edit_annotation(&ctx, &data, &annotation),
+                    _ => {
+                        close_popup();
+                        select_annotation(&data, &annotation)
+                    }
                 }
+                .unwrap_throw();
+            }) as Closure<dyn FnMut(MouseEvent)>,
+            |t, f| t.add_event_listener_with_callback("click", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("click", f)),
+        ));
+    }
+
+    let subscription = {
+        let items = items.clone();
 
-                notes_and_highlights.append_child(&flagged_container)?;
+        let func: ListenerEvent = Rc::new(RefCell::new(move |_, event: &EditorEvent| {
+            if *event == EditorEvent::Changed {
+                render_items(&ctx, &data, &items, &annotations).unwrap_throw();
             }
-        }
-    }
+        }));
+
+        listener_id
+            .try_get()
+            .map(|listener| listener.borrow_mut().subscribers.add(func))
+    };
 
     content.append_child(&inner)?;
     body().append_child(&modal)?;
 
     let popup = Popup {
+        events: element_events,
+        listener_id,
+        subscription,
         content: modal,
-        cancel_fn,
     };
 
     // TODO: Replace with set once stable.
@@ -160,3 +225,149 @@ fn show_popup(ctx: Context<List>) -> Result<(), JsValue> {
 
     Ok(())
 }
+
+#[cfg(feature = "frontend")]
+/// Replaces the items with the current annotations.
+fn render_items(
+    ctx: &Context<List>,
+    data: &SharedListenerData,
+    items: &Element,
+    annotations: &RefCell<Vec<Annotation>>,
+) -> Result<(), JsValue> {
+    items.set_inner_html("");
+
+    let data = data.upgrade().expect_throw("data upgrade");
+    let data = data.borrow();
+
+    let found = data.annotations();
+
+    for (index, annotation) in found.iter().enumerate() {
+        let flagged_container: HtmlElement = ctx.document.create_element("div")?.unchecked_into();
+        flagged_container
+            .class_list()
+            .add_1("editor-flagged-item")?;
+        flagged_container.set_attribute("data-index", &index.to_string())?;
+
+        // TODO: Allow for handling custom data.
+
+        let text = data
+            .annotation_nodes(annotation)
+            .iter()
+            .map(|v| v.data())
+            .collect::<String>();
+
+        let content: HtmlElement = ctx.document.create_element("p")?.unchecked_into();
+        content.class_list().add_1("editor-content")?;
+        content.set_inner_text(&text);
+        flagged_container.append_child(&content)?;
+
+        let footer = ctx.document.create_element("div")?;
+        footer.class_list().add_1("editor-footer")?;
+        flagged_container.append_child(&footer)?;
+
+        if let Some(component) = data.components.get(annotation.flag) {
+            let title: HtmlElement = ctx.document.create_element("span")?.unchecked_into();
+            title.set_inner_text(component.title());
+            footer.append_child(&title)?;
+        }
+
+        let remove: HtmlElement = ctx.document.create_element("span")?.unchecked_into();
+        remove.class_list().add_1("editor-clickable")?;
+        remove.set_attribute("data-action", "remove")?;
+        remove.set_inner_text("Remove");
+        footer.append_child(&remove)?;
+
+        if annotation.flag == Note::FLAG {
+            let edit: HtmlElement = ctx.document.create_element("span")?.unchecked_into();
+            edit.class_list().add_1("editor-clickable")?;
+            edit.set_attribute("data-action", "edit")?;
+            edit.set_inner_text("Edit");
+            footer.append_child(&edit)?;
+        }
+
+        items.append_child(&flagged_container)?;
+    }
+
+    *annotations.borrow_mut() = found;
+
+    Ok(())
+}
+
+#[cfg(feature = "frontend")]
+/// Removes the annotation. Notes also lose their data.
+fn remove_annotation(
+    ctx: &Context<List>,
+    data: &SharedListenerData,
+    annotation: &Annotation,
+) -> Result<()> {
+    let removed = data
+        .upgrade()
+        .expect_throw("data upgrade")
+        .borrow_mut()
+        .remove_annotation(annotation, annotation.flag == Note::FLAG)?;
+
+    // Refreshes the list through our subscription.
+    if removed {
+        ctx.save();
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "frontend")]
+/// Opens the editor of the annotations' Component.
+fn edit_annotation(
+    ctx: &Context<List>,
+    data: &SharedListenerData,
+    annotation: &Annotation,
+) -> Result<()> {
+    let Some(id) = annotation.data else {
+        return Ok(());
+    };
+
+    let (component, nodes) = {
+        let data = data.upgrade().expect_throw("data upgrade");
+        let data = data.borrow();
+
+        (
+            data.components.get(annotation.flag).cloned(),
+            data.annotation_nodes(annotation),
+        )
+    };
+
+    let Some(component) = component.filter(|_| !nodes.is_empty()) else {
+        return Ok(());
+    };
+
+    let container = create_container(nodes, data.clone())?;
+
+    component.on_edit(Rc::new(RefCell::new(container)), ctx.document.clone(), id)
+}
+
+#[cfg(feature = "frontend")]
+/// Scrolls to the annotation and selects its' text.
+fn select_annotation(data: &SharedListenerData, annotation: &Annotation) -> Result<()> {
+    let nodes = data
+        .upgrade()
+        .expect_throw("data upgrade")
+        .borrow()
+        .annotation_nodes(annotation);
+
+    let (Some(first), Some(last)) = (nodes.first(), nodes.last()) else {
+        return Ok(());
+    };
+
+    if let Some(element) = first.parent_element() {
+        element.scroll_into_view();
+    }
+
+    let range = Range::new()?;
+    range.set_start(first, 0)?;
+    range.set_end(last, last.length())?;
+
+    let selection = window().get_selection()?.unwrap_throw();
+    selection.remove_all_ranges()?;
+    selection.add_range(&range)?;
+
+    Ok(())
+}
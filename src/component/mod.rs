@@ -1,26 +1,38 @@
-use std::{borrow::Cow, cell::RefCell, marker::PhantomData, rc::Rc};
+use std::borrow::Cow;
+#[cfg(feature = "frontend")]
+use std::{cell::RefCell, marker::PhantomData, rc::Rc};
 
-use bitflags::bitflags;
+#[cfg(feature = "frontend")]
 use gloo_utils::window;
-use num_enum::TryFromPrimitive;
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(feature = "frontend")]
+use serde::Serialize;
+#[cfg(feature = "frontend")]
 use wasm_bindgen::UnwrapThrowExt;
+#[cfg(feature = "frontend")]
+use web_sys::Document;
 
+mod bold;
+mod data;
 mod highlight;
 mod italicize;
 mod list;
 mod note;
+mod registry;
 mod underline;
 
+pub use bold::*;
+pub use data::*;
 pub use highlight::*;
 pub use italicize::*;
 pub use list::*;
 pub use note::*;
+pub use registry::*;
 pub use underline::*;
-use web_sys::Document;
 
-use crate::{selection::NodeContainer, Result};
+#[cfg(feature = "frontend")]
+use crate::{listener::notify_changed, selection::NodeContainer, Result};
 
+#[cfg(feature = "frontend")]
 pub static STYLING_PREFIX_CLASS: &str = "editor-styling";
 
 pub trait Component: Sized {
@@ -28,23 +40,69 @@ pub trait Component: Sized {
     const TITLE: &'static str;
 
     /// The flag the component consists of.
+    ///
+    /// Custom Components should use [`ComponentFlag::custom`].
     const FLAG: ComponentFlag;
 
+    /// The class added to the text which contains this component.
+    const CLASS_NAME: Option<&'static str> = None;
+
+    /// Should the component have a button in the toolbar?
+    const SHOW_BUTTON: bool = true;
+
     /// The Allowed Components that are able to share this components' space.
     const ALLOWED_SIBLINGS: ComponentFlag = ComponentFlag::all();
 
     /// Should this component overwrite invalid siblings before inserting itself?
     const OVERWRITE_INVALID: bool = false;
 
+    /// Can text contain the component multiple times with different data? e.g. overlapping notes.
+    ///
+    /// Otherwise inserting the component replaces its' data.
+    const OVERLAPPING: bool = false;
+
     type Data: ComponentData;
 
+    #[cfg(feature = "frontend")]
     fn on_click_button(&self, ctx: &Context<Self>) -> Result<()>;
 
+    #[cfg(feature = "frontend")]
     /// If we've clicked text with the Component inside it.
     fn on_click(&self, _ctx: &Context<Self>) -> Result<()> {
         Ok(())
     }
 
+    #[cfg(feature = "frontend")]
+    /// Options shown when the toolbar button is held down. The button can't be expanded if empty.
+    fn expanded_options(&self, _ctx: &Context<Self>) -> Vec<ExpandedOption> {
+        Vec::new()
+    }
+
+    #[cfg(feature = "frontend")]
+    /// If we've clicked one of the [`Component::expanded_options`].
+    fn on_click_option(&self, _ctx: &Context<Self>, _data: u32) -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(feature = "frontend")]
+    /// Opens the editor of the stored data. Used by the [`List`] panel.
+    ///
+    /// The context contains the text of the annotation being edited.
+    fn on_edit(&self, _ctx: &Context<Self>, _data: u32) -> Result<()> {
+        Ok(())
+    }
+
+    /// CSS rules created from the listeners' stored data.
+    fn stylesheet(&self, _data: &[ComponentDataStore]) -> Option<String> {
+        None
+    }
+
+    /// The data id which points to the stored data at `index`.
+    fn data_id_for_index(index: u32) -> u32 {
+        index
+    }
+
+    #[cfg(feature = "frontend")]
     fn does_selected_contain_self(nodes: &NodeContainer) -> bool {
         nodes.does_selected_contain(&FlagsWithData::new_flag(Self::FLAG))
     }
@@ -80,24 +138,16 @@ impl ComponentData for () {
     fn from_id(_value: u32) -> Self {}
 }
 
-/// `ComponentFlag` is used to determine the type of component the Store is for.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct ComponentDataStore(pub(crate) ComponentFlag, pub(crate) String);
-
-impl ComponentDataStore {
-    pub fn new<S: Serialize>(flag: ComponentFlag, value: &S) -> Self {
-        Self(flag, serde_json::to_string(value).unwrap())
-    }
-
-    pub fn update<S: Serialize>(&mut self, value: &S) {
-        self.1 = serde_json::to_string(value).unwrap();
-    }
-
-    pub fn parse<D: DeserializeOwned>(&self) -> D {
-        serde_json::from_str(&self.1).unwrap()
-    }
+#[cfg(feature = "frontend")]
+/// An option of an expanded toolbar button.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedOption {
+    pub title: Cow<'static, str>,
+    /// The data id the option applies.
+    pub data: u32,
 }
 
+#[cfg(feature = "frontend")]
 pub struct Context<D: Component> {
     pub nodes: Rc<RefCell<NodeContainer>>,
     pub document: Document,
@@ -105,12 +155,13 @@ pub struct Context<D: Component> {
     _phantom: PhantomData<D>,
 }
 
+#[cfg(feature = "frontend")]
 impl<D: Component> Context<D> {
     pub fn new(nodes: Rc<RefCell<NodeContainer>>, document: Document) -> Self {
         Self {
             nodes,
             document,
-            _phantom: PhantomData::default(),
+            _phantom: PhantomData,
         }
     }
 
@@ -138,6 +189,38 @@ impl<D: Component> Context<D> {
             .get_data(D::FLAG, index)
     }
 
+    /// Returns every stored data of the Component with its' index.
+    pub fn get_all_data(&self) -> Vec<(u32, ComponentDataStore)> {
+        self.nodes
+            .borrow()
+            .data
+            .upgrade()
+            .expect_throw("data upgrade")
+            .borrow()
+            .data
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.0 == D::FLAG)
+            .map(|(i, v)| (i as u32, v.clone()))
+            .collect()
+    }
+
+    /// Returns the stable id of the data at the index.
+    pub fn get_annotation_id(&self, index: u32) -> AnnotationId {
+        self.get_data(index).id()
+    }
+
+    /// Returns the current index of the data with the stable id.
+    pub fn find_data_index(&self, id: AnnotationId) -> Option<u32> {
+        self.nodes
+            .borrow()
+            .data
+            .upgrade()
+            .expect_throw("data upgrade")
+            .borrow()
+            .find_data_index(id)
+    }
+
     pub fn update_data<S: Serialize>(&self, index: u32, value: &S) {
         self.nodes
             .borrow()
@@ -180,252 +263,31 @@ impl<D: Component> Context<D> {
     }
 
     pub fn save(&self) {
-        let id = self
-            .nodes
-            .borrow()
-            .data
-            .upgrade()
-            .expect_throw("data upgrade")
-            .borrow()
-            .listener_id;
+        let id = {
+            let data = self
+                .nodes
+                .borrow()
+                .data
+                .upgrade()
+                .expect_throw("data upgrade");
+            let mut data = data.borrow_mut();
+
+            data.commit();
+
+            data.listener_id
+        };
 
-        id.try_get().unwrap().borrow().on_event.borrow()(id);
+        notify_changed(id);
     }
 }
 
+#[cfg(feature = "frontend")]
 impl<D: Component> Clone for Context<D> {
     fn clone(&self) -> Self {
         Self {
             nodes: self.nodes.clone(),
             document: self.document.clone(),
-            _phantom: PhantomData::default(),
-        }
-    }
-}
-
-bitflags! {
-    #[derive(Serialize, Deserialize)]
-    pub struct ComponentFlag: u32 {
-        const ITALICIZE = 0b0000_0001;
-        const HIGHLIGHT = 0b0000_0010;
-        const UNDERLINE = 0b0000_0100;
-        const NOTE      = 0b0000_1000;
-        const LIST      = 0b0001_0000;
-    }
-}
-
-static COMPONENT_FLAGS: [ComponentFlag; 4] = [
-    ComponentFlag::ITALICIZE,
-    ComponentFlag::HIGHLIGHT,
-    ComponentFlag::UNDERLINE,
-    ComponentFlag::NOTE,
-];
-
-impl ComponentFlag {
-    pub fn separate_bits(self) -> Vec<Self> {
-        let mut flags = Vec::new();
-
-        COMPONENT_FLAGS.into_iter().for_each(|v| {
-            if self.contains(v) {
-                flags.push(v);
-            }
-        });
-
-        flags
-    }
-
-    // TODO: Replace with get_data() but we're using Self: Sized for the trait
-    pub fn get_data_class(self, value: u32) -> Option<Cow<'static, str>> {
-        match self {
-            Self::HIGHLIGHT => HighlightTypes::try_from_primitive(value as u8)
-                .unwrap()
-                .get_css(),
-
-            _ => None,
-        }
-    }
-
-    pub fn into_class_names(self) -> String {
-        let mut classes = Vec::new();
-
-        if self.contains(Self::ITALICIZE) {
-            classes.push("editor-italicize");
+            _phantom: PhantomData,
         }
-
-        if self.contains(Self::HIGHLIGHT) {
-            classes.push("editor-highlight");
-        }
-
-        if self.contains(Self::UNDERLINE) {
-            classes.push("editor-underline");
-        }
-
-        if self.contains(Self::NOTE) {
-            classes.push("editor-note");
-        }
-
-        classes.join(" ")
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct FlagsWithData {
-    pub flag: ComponentFlag,
-    /// Singular Flag with data.
-    pub data: Vec<(ComponentFlag, u32)>,
-}
-
-impl FlagsWithData {
-    pub fn new_flag(flag: ComponentFlag) -> Self {
-        Self {
-            flag,
-            data: Vec::new(),
-        }
-    }
-
-    pub fn new_with_data(flag: ComponentFlag, data: u32) -> Self {
-        Self {
-            flag,
-            data: vec![(flag, data)],
-        }
-    }
-
-    pub fn empty() -> Self {
-        Self {
-            flag: ComponentFlag::empty(),
-            data: Vec::new(),
-        }
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.flag.is_empty()
-    }
-
-    /// Returns true if there are flags common to both.
-    pub fn intersects_flag(&self, value: ComponentFlag) -> bool {
-        self.flag.intersects(value)
-    }
-
-    // TODO: Rename to Contains Flag
-    pub fn contains(&self, value: &Self) -> bool {
-        if self.flag.contains(value.flag) {
-            for a in &value.data {
-                if !self.data.iter().any(|b| b == a) {
-                    return false;
-                }
-            }
-
-            true
-        } else {
-            false
-        }
-    }
-
-    pub fn insert(&mut self, value: Self) {
-        self.flag.insert(value.flag);
-
-        for &(flag, new_data) in &value.data {
-            if let Some(data) = self.data.iter_mut().find(|v| v.0 == flag) {
-                data.1 = new_data;
-            } else {
-                self.data.push((flag, new_data));
-            }
-        }
-
-        self.data.sort_unstable();
-    }
-
-    pub fn clear(&mut self) {
-        self.flag = ComponentFlag::empty();
-        self.data.clear();
-    }
-
-    pub fn remove(&mut self, value: &Self) {
-        self.flag.remove(value.flag);
-
-        for &(flag, _) in &value.data {
-            if let Some(idx) = self.data.iter().position(|v| v.0 == flag) {
-                self.data.remove(idx);
-            }
-        }
-
-        self.data.sort_unstable();
-    }
-
-    pub fn generate_class_name(&self) -> String {
-        let mut classes = self.flag.into_class_names();
-
-        for (flag, data) in &self.data {
-            if let Some(css) = flag.get_data_class(*data) {
-                classes += " ";
-                classes += &css;
-            }
-        }
-
-        classes
-    }
-
-    pub fn into_singles_vec(&self) -> Vec<SingleFlagWithData> {
-        COMPONENT_FLAGS
-            .into_iter()
-            .filter_map(|v| {
-                if self.flag.contains(v) {
-                    let data = self
-                        .data
-                        .iter()
-                        .find_map(|(flag, data)| if &v == flag { Some(*data) } else { None })
-                        .unwrap_or_default();
-
-                    Some(SingleFlagWithData::new(v, data))
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
-    pub fn from_singles(value: &[SingleFlagWithData]) -> Self {
-        let mut this = Self::empty();
-
-        for item in value {
-            this.flag.insert(item.flag());
-            this.data.push((item.flag(), item.data()));
-        }
-
-        this.data.sort_unstable();
-
-        this
-    }
-}
-
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-#[serde(transparent)]
-pub struct SingleFlagWithData(pub(crate) u64);
-// TODO: Replace u64 w/ enum for Component, Data (array)
-
-impl SingleFlagWithData {
-    pub fn new(flag: ComponentFlag, data: u32) -> Self {
-        Self((flag.bits() as u64) << 32 | (data as u64))
-    }
-
-    pub fn empty() -> Self {
-        Self(0)
-    }
-
-    pub fn flag(self) -> ComponentFlag {
-        ComponentFlag::from_bits_truncate((self.0 >> 32) as u32)
-    }
-
-    pub fn data(self) -> u32 {
-        (self.0 & 0xFF) as u32
-    }
-}
-
-impl std::fmt::Debug for SingleFlagWithData {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("SingleFlagWithData")
-            .field(&self.flag())
-            .field(&self.data())
-            .finish()
     }
 }
@@ -1,72 +1,343 @@
+#[cfg(feature = "frontend")]
 use std::cell::RefCell;
 
+use chrono::{DateTime, Utc};
+#[cfg(feature = "frontend")]
 use gloo_utils::body;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "frontend")]
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue, UnwrapThrowExt};
-use web_sys::{Element, HtmlElement, HtmlTextAreaElement, MouseEvent};
+#[cfg(feature = "frontend")]
+use web_sys::{Element, HtmlElement, HtmlInputElement, HtmlTextAreaElement, MouseEvent};
 
-use crate::{helper::TargetCast, util::ElementEvent, ComponentFlag, Result};
+#[cfg(feature = "frontend")]
+use crate::{helper::TargetCast, util::ElementEvent, Result};
+use crate::{markdown::render_markdown, ComponentFlag};
 
-use super::{Component, Context};
+use super::Component;
+#[cfg(feature = "frontend")]
+use super::Context;
 
-// TODO: Note should be the only component set for the nodes its' on.
+/// The default maximum length of a Notes' body.
+pub const DEFAULT_NOTE_MAX_LENGTH: u32 = 500;
 
-pub struct Note;
+pub struct Note {
+    /// Maximum length of a Notes' body in UTF-16 code units.
+    pub max_length: u32,
+}
+
+impl Note {
+    pub fn new(max_length: u32) -> Self {
+        Self { max_length }
+    }
+}
+
+impl Default for Note {
+    fn default() -> Self {
+        Self::new(DEFAULT_NOTE_MAX_LENGTH)
+    }
+}
+
+/// The data stored for every Note.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "NoteDataRepr")]
+pub struct NoteData {
+    /// Markdown
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl NoteData {
+    pub fn new(body: String) -> Self {
+        let now = Utc::now();
+
+        Self {
+            body,
+            created_at: now,
+            updated_at: now,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Changes the body and tags. Only updates the timestamp if something changed.
+    pub fn update(&mut self, body: String, tags: Vec<String>) {
+        if self.body != body || self.tags != tags {
+            self.body = body;
+            self.tags = tags;
+            self.updated_at = Utc::now();
+        }
+    }
+
+    /// The body rendered to HTML. Raw HTML and unsafe links are removed.
+    pub fn render_html(&self) -> String {
+        render_markdown(&self.body)
+    }
+}
+
+/// Notes used to only store their text.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NoteDataRepr {
+    Data {
+        body: String,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+    // The timestamps are unknown.
+    Text(String),
+}
+
+impl From<NoteDataRepr> for NoteData {
+    fn from(value: NoteDataRepr) -> Self {
+        match value {
+            NoteDataRepr::Data {
+                body,
+                created_at,
+                updated_at,
+                tags,
+            } => Self {
+                body,
+                created_at,
+                updated_at,
+                tags,
+            },
+            NoteDataRepr::Text(body) => Self {
+                body,
+                created_at: DateTime::UNIX_EPOCH,
+                updated_at: DateTime::UNIX_EPOCH,
+                tags: Vec::new(),
+            },
+        }
+    }
+}
+
+#[cfg(any(test, feature = "frontend"))]
+/// Splits the comma separated tags. Empty and duplicate tags are removed.
+fn parse_tags(value: &str) -> Vec<String> {
+    let mut tags = Vec::<String>::new();
+
+    for tag in value.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+        if !tags.iter().any(|v| v == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    tags
+}
 
 impl Component for Note {
     const FLAG: ComponentFlag = ComponentFlag::NOTE;
     const TITLE: &'static str = "Note";
+    const CLASS_NAME: Option<&'static str> = Some("editor-note");
 
-    const ALLOWED_SIBLINGS: ComponentFlag = ComponentFlag::empty();
+    const ALLOWED_SIBLINGS: ComponentFlag = ComponentFlag::NOTE;
     const OVERWRITE_INVALID: bool = true;
+    const OVERLAPPING: bool = true;
 
     type Data = ();
 
+    #[cfg(feature = "frontend")]
     fn on_click_button(&self, ctx: &Context<Self>) -> Result<()> {
         debug!("Note - Selected {}", ctx.get_selection_data_ids().len());
 
-        show_popup(None, ctx.clone())?;
+        show_popup(None, self.max_length, ctx.clone())?;
 
         Ok(())
     }
 
+    #[cfg(feature = "frontend")]
     fn on_click(&self, ctx: &Context<Self>) -> Result<()> {
         info!("on_click");
 
-        for (clicked_flag, id) in ctx.get_selection_data_ids() {
-            if clicked_flag == Self::FLAG {
-                show_popup(Some(id), ctx.clone())?;
-
-                break;
-            }
+        let mut ids = ctx
+            .get_selection_data_ids()
+            .into_iter()
+            .filter(|(flag, _)| *flag == Self::FLAG)
+            .map(|(_, id)| id)
+            .collect::<Vec<_>>();
+
+        ids.sort_unstable();
+        ids.dedup();
+
+        match ids[..] {
+            [] => (),
+            [id] => show_popup(Some(id), self.max_length, ctx.clone())?,
+            // Overlapping notes. Let the user pick which one to open.
+            _ => show_picker(&ids, self.max_length, ctx.clone())?,
         }
 
         Ok(())
     }
+
+    #[cfg(feature = "frontend")]
+    fn on_edit(&self, ctx: &Context<Self>, data: u32) -> Result<()> {
+        show_popup(Some(data), self.max_length, ctx.clone())?;
+
+        Ok(())
+    }
 }
 
+#[cfg(feature = "frontend")]
 thread_local! {
     static DISPLAYING: RefCell<Option<Popup>> = RefCell::default();
 }
 
+#[cfg(feature = "frontend")]
 #[allow(dead_code)]
 struct Popup {
     events: Vec<ElementEvent>,
 
-    text_area: HtmlTextAreaElement,
+    text_area: Option<HtmlTextAreaElement>,
+    tags: Option<HtmlInputElement>,
     content: Element,
 }
 
+#[cfg(feature = "frontend")]
 impl Popup {
     pub fn close(self) {
         self.content.remove();
     }
 
     pub fn value(&self) -> String {
-        self.text_area.value()
+        self.text_area
+            .as_ref()
+            .map(|v| v.value())
+            .unwrap_or_default()
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        self.tags
+            .as_ref()
+            .map(|v| parse_tags(&v.value()))
+            .unwrap_or_default()
     }
 }
 
-fn show_popup(editing_id: Option<u32>, ctx: Context<Note>) -> Result<(), JsValue> {
+#[cfg(feature = "frontend")]
+/// How many characters of a note are shown in the picker.
+const PREVIEW_LENGTH: usize = 60;
+
+#[cfg(feature = "frontend")]
+/// Lists the overlapping notes. Clicking one opens it.
+fn show_picker(ids: &[u32], max_length: u32, ctx: Context<Note>) -> Result<(), JsValue> {
+    let close_fn = || {
+        Closure::new(|e: MouseEvent| {
+            let target = e.target_unchecked_into::<HtmlElement>();
+
+            if target.class_list().contains("modal") || target.class_list().contains("btn-close") {
+                // Stop propagation so we don't open the popup again
+                e.stop_propagation();
+
+                DISPLAYING.with(|popup| {
+                    if let Some(popup) = popup.take() {
+                        popup.close();
+                    }
+                });
+            }
+        }) as Closure<dyn FnMut(MouseEvent)>
+    };
+
+    let mut element_events = Vec::new();
+
+    let modal = ctx.document.create_element("div")?;
+    modal.class_list().add_2("modal", "d-block")?;
+    modal.set_attribute("tabindex", "-1")?;
+    element_events.push(ElementEvent::link(
+        modal.clone().unchecked_into(),
+        close_fn(),
+        |t, f| t.add_event_listener_with_callback("click", f),
+        Box::new(|t, f| t.remove_event_listener_with_callback("click", f)),
+    ));
+
+    let content = ctx.document.create_element("div")?;
+    content.class_list().add_1("modal-dialog")?;
+    modal.append_child(&content)?;
+
+    let inner = ctx.document.create_element("div")?;
+    inner.class_list().add_1("modal-content")?;
+    content.append_child(&inner)?;
+
+    {
+        let header = ctx.document.create_element("div")?;
+        header.class_list().add_1("modal-header")?;
+        inner.append_child(&header)?;
+
+        let title: HtmlElement = ctx.document.create_element("h3")?.unchecked_into();
+        title.set_inner_text("Notes");
+        header.append_child(&title)?;
+
+        let cancel: HtmlElement = ctx
+            .document
+            .create_element("editor-button")?
+            .unchecked_into();
+        cancel.set_attribute("aria-label", "Close")?;
+        cancel.class_list().add_1("btn-close")?;
+        header.append_child(&cancel)?;
+    }
+
+    let list = ctx.document.create_element("div")?;
+    list.class_list().add_2("modal-body", "list-group")?;
+    inner.append_child(&list)?;
+
+    for &id in ids {
+        let value = ctx.get_data(id).parse::<NoteData>().body;
+
+        let item: HtmlElement = ctx
+            .document
+            .create_element("editor-button")?
+            .unchecked_into();
+        item.class_list()
+            .add_2("list-group-item", "list-group-item-action")?;
+        item.set_inner_text(&value.chars().take(PREVIEW_LENGTH).collect::<String>());
+        list.append_child(&item)?;
+
+        let ctx = ctx.clone();
+
+        element_events.push(ElementEvent::link(
+            item.unchecked_into(),
+            Closure::new(move |e: MouseEvent| {
+                e.stop_propagation();
+
+                let previous = DISPLAYING.with(|popup| popup.take());
+
+                if let Some(popup) = previous {
+                    popup.close();
+                }
+
+                show_popup(Some(id), max_length, ctx.clone()).unwrap_throw();
+            }) as Closure<dyn FnMut(MouseEvent)>,
+            |t, f| t.add_event_listener_with_callback("click", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("click", f)),
+        ));
+    }
+
+    body().append_child(&modal)?;
+
+    DISPLAYING.with(move |v| {
+        *v.borrow_mut() = Some(Popup {
+            events: element_events,
+
+            text_area: None,
+            tags: None,
+            content: modal,
+        });
+    });
+
+    Ok(())
+}
+
+#[cfg(feature = "frontend")]
+fn show_popup(editing_id: Option<u32>, max_length: u32, ctx: Context<Note>) -> Result<(), JsValue> {
     let close_popup_fn: Closure<dyn FnMut(MouseEvent)> = Closure::new(|e: MouseEvent| {
         if e.target_unchecked_into::<HtmlElement>()
             .class_list()
@@ -117,10 +388,14 @@ fn show_popup(editing_id: Option<u32>, ctx: Context<Note>) -> Result<(), JsValue
                 let popup = popup.take().unwrap_throw();
 
                 if let Some(editing_id) = editing_id {
-                    ctx.update_data(editing_id, &popup.value());
+                    let mut note = ctx.get_data(editing_id).parse::<NoteData>();
+                    note.update(popup.value(), popup.tags());
+
+                    ctx.update_data(editing_id, &note);
                 } else {
                     ctx.reload_section().unwrap_throw();
-                    let data_pos = ctx.store_data(&popup.value());
+                    let note = NoteData::new(popup.value()).with_tags(popup.tags());
+                    let data_pos = ctx.store_data(&note);
                     if let Err(_e) = ctx.insert_selection(Some(data_pos)).unwrap_throw() {
                         // Remove Inserted data if we're unable to insert
                         ctx.remove_data(data_pos);
@@ -181,7 +456,9 @@ fn show_popup(editing_id: Option<u32>, ctx: Context<Note>) -> Result<(), JsValue
         ));
     }
 
-    let text_area = {
+    let editing = editing_id.map(|id| ctx.get_data(id).parse::<NoteData>());
+
+    let (text_area, tags) = {
         let body = ctx.document.create_element("div")?;
         body.class_list().add_1("modal-body")?;
         inner.append_child(&body)?;
@@ -189,15 +466,47 @@ fn show_popup(editing_id: Option<u32>, ctx: Context<Note>) -> Result<(), JsValue
         let text_area: HtmlTextAreaElement =
             ctx.document.create_element("textarea")?.unchecked_into();
         text_area.class_list().add_1("form-control")?;
-        text_area.set_max_length(500);
+        text_area.set_max_length(max_length as i32);
         text_area.set_spellcheck(true);
+        text_area.set_placeholder("Markdown");
         body.append_child(&text_area)?;
 
-        if let Some(editing_id) = editing_id {
-            text_area.set_value(&ctx.get_data(editing_id).parse::<String>())
+        let tags: HtmlInputElement = ctx.document.create_element("input")?.unchecked_into();
+        tags.class_list().add_2("form-control", "mt-2")?;
+        tags.set_placeholder("Tags, separated by commas");
+        body.append_child(&tags)?;
+
+        let preview = ctx.document.create_element("div")?;
+        preview.class_list().add_2("editor-note-preview", "mt-2")?;
+        body.append_child(&preview)?;
+
+        if let Some(note) = editing.as_ref() {
+            text_area.set_value(&note.body);
+            tags.set_value(&note.tags.join(", "));
+            preview.set_inner_html(&note.render_html());
+
+            let dates: HtmlElement = ctx.document.create_element("small")?.unchecked_into();
+            dates.class_list().add_1("text-muted")?;
+            dates.set_inner_text(&format!(
+                "Created {} · Updated {}",
+                note.created_at.format("%Y-%m-%d %H:%M"),
+                note.updated_at.format("%Y-%m-%d %H:%M")
+            ));
+            body.append_child(&dates)?;
         }
 
-        text_area
+        // Update the preview while typing.
+        element_events.push(ElementEvent::link(
+            text_area.clone().unchecked_into(),
+            Closure::new(move |e: web_sys::Event| {
+                let text_area = e.target_unchecked_into::<HtmlTextAreaElement>();
+                preview.set_inner_html(&render_markdown(&text_area.value()));
+            }) as Closure<dyn FnMut(web_sys::Event)>,
+            |t, f| t.add_event_listener_with_callback("input", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("input", f)),
+        ));
+
+        (text_area, tags)
     };
 
     {
@@ -259,7 +568,8 @@ fn show_popup(editing_id: Option<u32>, ctx: Context<Note>) -> Result<(), JsValue
     let popup = Popup {
         events: element_events,
 
-        text_area,
+        text_area: Some(text_area),
+        tags: Some(tags),
         content: modal,
     };
 
@@ -270,3 +580,40 @@ fn show_popup(editing_id: Option<u32>, ctx: Context<Note>) -> Result<(), JsValue
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::component::ComponentDataStore;
+
+    use super::*;
+
+    #[test]
+    fn note_data_from_text() {
+        // Notes used to only store their text.
+        let store = ComponentDataStore::new(ComponentFlag::NOTE, &"Old *note*");
+        let note = store.parse::<NoteData>();
+
+        assert_eq!(note.body, "Old *note*");
+        assert_eq!(note.created_at, DateTime::UNIX_EPOCH);
+        assert!(note.tags.is_empty());
+        assert_eq!(note.render_html(), "<p>Old <em>note</em></p>\n");
+
+        let note = NoteData::new(String::from("New")).with_tags(parse_tags("a, b,, a ,c"));
+        assert_eq!(note.tags, ["a", "b", "c"]);
+
+        let store = ComponentDataStore::new(ComponentFlag::NOTE, &note);
+        assert_eq!(store.parse::<NoteData>(), note);
+    }
+
+    #[test]
+    fn note_data_update() {
+        let mut note = NoteData::new(String::from("Body"));
+        note.updated_at = DateTime::UNIX_EPOCH;
+
+        note.update(String::from("Body"), Vec::new());
+        assert_eq!(note.updated_at, DateTime::UNIX_EPOCH);
+
+        note.update(String::from("Body"), vec![String::from("tag")]);
+        assert!(note.updated_at > DateTime::UNIX_EPOCH);
+    }
+}
@@ -0,0 +1,316 @@
+#[cfg(feature = "frontend")]
+use std::cell::RefCell;
+use std::{borrow::Cow, rc::Rc};
+
+#[cfg(feature = "frontend")]
+use web_sys::Document;
+
+use crate::ComponentFlag;
+#[cfg(feature = "frontend")]
+use crate::{selection::NodeContainer, Result};
+
+use super::{
+    Bold, Component, ComponentData, ComponentDataStore, FlagsWithData, Highlight, Italicize, List,
+    Note, Underline,
+};
+#[cfg(feature = "frontend")]
+use super::{Context, ExpandedOption};
+
+/// Object safe version of [`Component`]. Implemented for every `Component`.
+pub trait DynComponent {
+    fn title(&self) -> &'static str;
+
+    fn flag(&self) -> ComponentFlag;
+
+    fn class_name(&self) -> Option<&'static str>;
+
+    fn data_class(&self, data: u32) -> Option<Cow<'static, str>>;
+
+    fn show_button(&self) -> bool;
+
+    fn overlapping(&self) -> bool;
+
+    #[cfg(feature = "frontend")]
+    fn on_click_button(&self, nodes: Rc<RefCell<NodeContainer>>, document: Document) -> Result<()>;
+
+    #[cfg(feature = "frontend")]
+    fn on_click(&self, nodes: Rc<RefCell<NodeContainer>>, document: Document) -> Result<()>;
+
+    #[cfg(feature = "frontend")]
+    fn expanded_options(
+        &self,
+        nodes: Rc<RefCell<NodeContainer>>,
+        document: Document,
+    ) -> Vec<ExpandedOption>;
+
+    #[cfg(feature = "frontend")]
+    fn on_click_option(
+        &self,
+        nodes: Rc<RefCell<NodeContainer>>,
+        document: Document,
+        data: u32,
+    ) -> Result<()>;
+
+    #[cfg(feature = "frontend")]
+    fn on_edit(
+        &self,
+        nodes: Rc<RefCell<NodeContainer>>,
+        document: Document,
+        data: u32,
+    ) -> Result<()>;
+
+    fn stylesheet(&self, data: &[ComponentDataStore]) -> Option<String>;
+
+    fn data_id_for_index(&self, index: u32) -> u32;
+}
+
+impl<C: Component + 'static> DynComponent for C {
+    fn title(&self) -> &'static str {
+        C::TITLE
+    }
+
+    fn flag(&self) -> ComponentFlag {
+        C::FLAG
+    }
+
+    fn class_name(&self) -> Option<&'static str> {
+        C::CLASS_NAME
+    }
+
+    fn data_class(&self, data: u32) -> Option<Cow<'static, str>> {
+        <C::Data as ComponentData>::from_id(data).get_css()
+    }
+
+    fn show_button(&self) -> bool {
+        C::SHOW_BUTTON
+    }
+
+    fn overlapping(&self) -> bool {
+        C::OVERLAPPING
+    }
+
+    #[cfg(feature = "frontend")]
+    fn on_click_button(&self, nodes: Rc<RefCell<NodeContainer>>, document: Document) -> Result<()> {
+        Component::on_click_button(self, &Context::<C>::new(nodes, document))
+    }
+
+    #[cfg(feature = "frontend")]
+    fn on_click(&self, nodes: Rc<RefCell<NodeContainer>>, document: Document) -> Result<()> {
+        Component::on_click(self, &Context::<C>::new(nodes, document))
+    }
+
+    #[cfg(feature = "frontend")]
+    fn expanded_options(
+        &self,
+        nodes: Rc<RefCell<NodeContainer>>,
+        document: Document,
+    ) -> Vec<ExpandedOption> {
+        Component::expanded_options(self, &Context::<C>::new(nodes, document))
+    }
+
+    #[cfg(feature = "frontend")]
+    fn on_click_option(
+        &self,
+        nodes: Rc<RefCell<NodeContainer>>,
+        document: Document,
+        data: u32,
+    ) -> Result<()> {
+        Component::on_click_option(self, &Context::<C>::new(nodes, document), data)
+    }
+
+    #[cfg(feature = "frontend")]
+    fn on_edit(
+        &self,
+        nodes: Rc<RefCell<NodeContainer>>,
+        document: Document,
+        data: u32,
+    ) -> Result<()> {
+        Component::on_edit(self, &Context::<C>::new(nodes, document), data)
+    }
+
+    fn stylesheet(&self, data: &[ComponentDataStore]) -> Option<String> {
+        Component::stylesheet(self, data)
+    }
+
+    fn data_id_for_index(&self, index: u32) -> u32 {
+        C::data_id_for_index(index)
+    }
+}
+
+/// The Components a listener knows about.
+///
+/// Class names, click handling and toolbar buttons are all looked up through here.
+#[derive(Clone)]
+pub struct ComponentRegistry {
+    components: Vec<Rc<dyn DynComponent>>,
+}
+
+impl ComponentRegistry {
+    /// Creates a registry without any Components.
+    pub fn empty() -> Self {
+        Self {
+            components: Vec::new(),
+        }
+    }
+
+    /// Registers the Component.
+    ///
+    /// Panics if the flag isn't a single bit or if it's already registered.
+    pub fn register<C: Component + 'static>(&mut self, component: C) -> &mut Self {
+        assert_eq!(
+            C::FLAG.bits().count_ones(),
+            1,
+            "Component Flag must be a single bit"
+        );
+
+        if self.get(C::FLAG).is_some() {
+            panic!("Component Flag {:?} is already registered!", C::FLAG);
+        }
+
+        self.components.push(Rc::new(component));
+
+        self
+    }
+
+    pub fn get(&self, flag: ComponentFlag) -> Option<&Rc<dyn DynComponent>> {
+        self.components.iter().find(|v| v.flag() == flag)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<dyn DynComponent>> {
+        self.components.iter()
+    }
+
+    /// Returns the registered Components contained in the flag.
+    pub fn get_all(&self, flag: ComponentFlag) -> Vec<Rc<dyn DynComponent>> {
+        flag.separate_bits()
+            .into_iter()
+            .filter_map(|v| self.get(v).cloned())
+            .collect()
+    }
+
+    pub fn generate_class_name(&self, flag: &FlagsWithData) -> String {
+        let mut classes = flag
+            .flag
+            .separate_bits()
+            .into_iter()
+            .filter_map(|v| self.get(v)?.class_name())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut data_classes = Vec::new();
+
+        for (flag, data) in &flag.data {
+            if let Some(css) = self.get(*flag).and_then(|v| v.data_class(*data)) {
+                if !data_classes.contains(&css) {
+                    data_classes.push(css);
+                }
+            }
+        }
+
+        for css in data_classes {
+            classes += " ";
+            classes += &css;
+        }
+
+        // The same Component is on the text more than once.
+        if flag
+            .flag
+            .separate_bits()
+            .into_iter()
+            .any(|v| flag.data_of(v).nth(1).is_some())
+        {
+            classes += " editor-overlap";
+        }
+
+        classes
+    }
+
+    /// Combined CSS rules of every Component.
+    pub fn stylesheet(&self, data: &[ComponentDataStore]) -> String {
+        self.components
+            .iter()
+            .filter_map(|v| v.stylesheet(data))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Default for ComponentRegistry {
+    /// Registry containing the built-in Components.
+    fn default() -> Self {
+        let mut this = Self::empty();
+
+        this.register(Highlight)
+            .register(Note::default())
+            .register(Bold)
+            .register(Italicize)
+            .register(Underline)
+            .register(List);
+
+        this
+    }
+}
+
+impl std::fmt::Debug for ComponentRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.components.iter().map(|v| v.title()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Vocabulary;
+
+    impl Component for Vocabulary {
+        const TITLE: &'static str = "Vocabulary";
+        const FLAG: ComponentFlag = ComponentFlag::custom(0);
+        const CLASS_NAME: Option<&'static str> = Some("vocabulary");
+
+        type Data = ();
+
+        #[cfg(feature = "frontend")]
+        fn on_click_button(&self, _ctx: &Context<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn class_names() {
+        let mut registry = ComponentRegistry::default();
+        registry.register(Vocabulary);
+
+        let mut flag = FlagsWithData::new_with_data(ComponentFlag::HIGHLIGHT, 1);
+        flag.insert(FlagsWithData::new_flag(ComponentFlag::ITALICIZE));
+        flag.insert(FlagsWithData::new_flag(Vocabulary::FLAG));
+
+        assert_eq!(
+            registry.generate_class_name(&flag),
+            "editor-italicize editor-highlight vocabulary orange"
+        );
+
+        // Unregistered Components are skipped.
+        assert_eq!(
+            ComponentRegistry::default().generate_class_name(&flag),
+            "editor-italicize editor-highlight orange"
+        );
+
+        let mut flag = FlagsWithData::new_with_data(ComponentFlag::HIGHLIGHT, 1);
+        flag.insert(FlagsWithData::new_with_data(ComponentFlag::HIGHLIGHT, 2));
+        flag.insert(FlagsWithData::new_with_data(ComponentFlag::NOTE, 0));
+
+        assert_eq!(
+            registry.generate_class_name(&flag),
+            "editor-highlight editor-note orange blue editor-overlap"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_flag() {
+        ComponentRegistry::default().register(Highlight);
+    }
+}
@@ -1,15 +1,21 @@
-use crate::{ComponentFlag, Result};
+use crate::ComponentFlag;
+#[cfg(feature = "frontend")]
+use crate::Result;
 
-use super::{Component, Context};
+use super::Component;
+#[cfg(feature = "frontend")]
+use super::Context;
 
 pub struct Underline;
 
 impl Component for Underline {
     const FLAG: ComponentFlag = ComponentFlag::UNDERLINE;
     const TITLE: &'static str = "Underline";
+    const CLASS_NAME: Option<&'static str> = Some("editor-underline");
 
     type Data = ();
 
+    #[cfg(feature = "frontend")]
     fn on_click_button(&self, ctx: &Context<Self>) -> Result<()> {
         debug!("Underline");
 
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{component::ComponentDataStore, SaveState, SavedNode};
+
+/// The changes between two revisions of a [`SaveState`].
+///
+/// Nodes are matched by their node index and data by its' index.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SaveDelta {
+    pub from_revision: u64,
+    pub to_revision: u64,
+
+    pub(crate) added_nodes: Vec<SavedNode>,
+    pub(crate) changed_nodes: Vec<SavedNode>,
+    /// Node indexes
+    pub(crate) removed_nodes: Vec<usize>,
+
+    pub(crate) added_data: Vec<(u32, ComponentDataStore)>,
+    pub(crate) changed_data: Vec<(u32, ComponentDataStore)>,
+    /// Data indexes. Always the last ones since data is swap removed.
+    pub(crate) removed_data: Vec<u32>,
+}
+
+impl SaveDelta {
+    /// Returns the changes needed to turn `before` into `after`.
+    pub fn between(before: &SaveState, after: &SaveState) -> Self {
+        let mut delta = Self::default();
+
+        for node in &after.nodes {
+            match before.nodes.iter().find(|v| v.index() == node.index()) {
+                Some(prev) if prev == node => (),
+                Some(_) => delta.changed_nodes.push(node.clone()),
+                None => delta.added_nodes.push(node.clone()),
+            }
+        }
+
+        delta.removed_nodes = before
+            .nodes
+            .iter()
+            .map(|v| v.index())
+            .filter(|&index| !after.nodes.iter().any(|v| v.index() == index))
+            .collect();
+
+        for (index, value) in after.data.iter().enumerate() {
+            match before.data.get(index) {
+                Some(prev) if prev == value => (),
+                Some(_) => delta.changed_data.push((index as u32, value.clone())),
+                None => delta.added_data.push((index as u32, value.clone())),
+            }
+        }
+
+        delta.removed_data = (after.data.len()..before.data.len())
+            .map(|v| v as u32)
+            .collect();
+
+        delta
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_data.is_empty()
+            && self.changed_data.is_empty()
+            && self.removed_data.is_empty()
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DeltaError {
+    #[error("No saved node with index {0}")]
+    MissingNode(usize),
+
+    #[error("A saved node with index {0} already exists")]
+    DuplicateNode(usize),
+
+    #[error("Data index {index} is invalid for {len} data items")]
+    DataIndex { index: u32, len: usize },
+}
+
+impl SaveState {
+    /// Applies the changes of the delta. Nothing is changed if the delta doesn't fit the state.
+    pub fn apply_delta(&mut self, delta: &SaveDelta) -> Result<(), DeltaError> {
+        self.validate_delta(delta)?;
+
+        self.nodes
+            .retain(|v| !delta.removed_nodes.contains(&v.index()));
+
+        for node in &delta.changed_nodes {
+            if let Some(found) = self.nodes.iter_mut().find(|v| v.index() == node.index()) {
+                *found = node.clone();
+            }
+        }
+
+        self.nodes.extend(delta.added_nodes.iter().cloned());
+        self.nodes.sort_by_key(|v| v.index());
+
+        for (index, value) in &delta.changed_data {
+            self.data[*index as usize] = value.clone();
+        }
+
+        self.data
+            .truncate(self.data.len() - delta.removed_data.len());
+
+        let mut added = delta.added_data.clone();
+        added.sort_by_key(|(index, _)| *index);
+
+        self.data.extend(added.into_iter().map(|(_, value)| value));
+
+        Ok(())
+    }
+
+    fn validate_delta(&self, delta: &SaveDelta) -> Result<(), DeltaError> {
+        let has_node = |index: usize| self.nodes.iter().any(|v| v.index() == index);
+
+        for &index in &delta.removed_nodes {
+            if !has_node(index) {
+                return Err(DeltaError::MissingNode(index));
+            }
+        }
+
+        for node in &delta.changed_nodes {
+            if !has_node(node.index()) {
+                return Err(DeltaError::MissingNode(node.index()));
+            }
+        }
+
+        for node in &delta.added_nodes {
+            if has_node(node.index()) && !delta.removed_nodes.contains(&node.index()) {
+                return Err(DeltaError::DuplicateNode(node.index()));
+            }
+        }
+
+        let len = self.data.len();
+        let remaining = len.saturating_sub(delta.removed_data.len());
+
+        let invalid_index = |index: u32| DeltaError::DataIndex { index, len };
+
+        // Removed data has to be the last items.
+        if delta.removed_data.len() > len {
+            return Err(invalid_index(delta.removed_data[0]));
+        }
+
+        for &index in &delta.removed_data {
+            if (index as usize) < remaining || index as usize >= len {
+                return Err(invalid_index(index));
+            }
+        }
+
+        for (index, _) in &delta.changed_data {
+            if *index as usize >= remaining {
+                return Err(invalid_index(*index));
+            }
+        }
+
+        let mut added = delta
+            .added_data
+            .iter()
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>();
+        added.sort_unstable();
+
+        for (offset, index) in added.into_iter().enumerate() {
+            if index as usize != remaining + offset {
+                return Err(invalid_index(index));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        component::SingleFlagWithData, migration::CURRENT_VERSION, ComponentFlag, SavedNodeFlag,
+    };
+
+    use super::*;
+
+    fn node(index: usize, offset: u32) -> SavedNode {
+        SavedNode::new(
+            index,
+            vec![SavedNodeFlag {
+                offset,
+                length: None,
+                flags: vec![SingleFlagWithData::new(ComponentFlag::NOTE, 0)],
+            }],
+        )
+    }
+
+    fn note(value: &str) -> ComponentDataStore {
+        ComponentDataStore::new(ComponentFlag::NOTE, &value)
+    }
+
+    fn state(nodes: Vec<SavedNode>, data: Vec<ComponentDataStore>) -> SaveState {
+        SaveState {
+            version: CURRENT_VERSION,
+            data,
+            nodes,
+        }
+    }
+
+    #[test]
+    fn apply_between() {
+        let before = state(
+            vec![node(0, 0), node(2, 0), node(5, 0)],
+            vec![note("a"), note("b"), note("c")],
+        );
+
+        let after = state(
+            vec![node(1, 0), node(2, 4), node(5, 0)],
+            vec![note("a"), note("d")],
+        );
+
+        let delta = SaveDelta::between(&before, &after);
+
+        assert_eq!(delta.added_nodes, [node(1, 0)]);
+        assert_eq!(delta.changed_nodes, [node(2, 4)]);
+        assert_eq!(delta.removed_nodes, [0]);
+        assert_eq!(delta.changed_data, [(1, note("d"))]);
+        assert_eq!(delta.removed_data, [2]);
+
+        let mut applied = before.clone();
+        applied.apply_delta(&delta).unwrap();
+        assert_eq!(applied, after);
+
+        // Back again adds the data.
+        let mut applied = after.clone();
+        applied
+            .apply_delta(&SaveDelta::between(&after, &before))
+            .unwrap();
+        assert_eq!(applied, before);
+
+        assert!(SaveDelta::between(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn apply_invalid() {
+        let mut save = state(vec![node(0, 0)], vec![note("a")]);
+
+        let delta = SaveDelta {
+            changed_nodes: vec![node(3, 0)],
+            ..Default::default()
+        };
+        assert_eq!(save.apply_delta(&delta), Err(DeltaError::MissingNode(3)));
+
+        let delta = SaveDelta {
+            added_nodes: vec![node(0, 2)],
+            ..Default::default()
+        };
+        assert_eq!(save.apply_delta(&delta), Err(DeltaError::DuplicateNode(0)));
+
+        let delta = SaveDelta {
+            added_data: vec![(4, note("b"))],
+            ..Default::default()
+        };
+        assert_eq!(
+            save.apply_delta(&delta),
+            Err(DeltaError::DataIndex { index: 4, len: 1 })
+        );
+
+        // Nothing was applied.
+        assert_eq!(save, state(vec![node(0, 0)], vec![note("a")]));
+    }
+}
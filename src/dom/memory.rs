@@ -0,0 +1,558 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use thiserror::Error;
+
+use super::Dom;
+
+/// Elements which never have children or a closing tag.
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose text is serialized without escaping.
+const RAW_TEXT_ELEMENTS: [&str; 8] = [
+    "style",
+    "script",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+    "noscript",
+];
+
+/// A DOM tree in memory. Behaves like the browser for the operations of [`Dom`].
+pub struct MemoryDom;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MemoryDomError {
+    #[error("Offset {offset} is past the end of the Text Node with length {length}")]
+    IndexSize { offset: u32, length: u32 },
+}
+
+/// A Text Node, Comment or Element of the [`MemoryDom`].
+#[derive(Clone)]
+pub struct MemoryNode(Rc<RefCell<NodeData>>);
+
+struct NodeData {
+    kind: NodeKind,
+    parent: Weak<RefCell<NodeData>>,
+    children: Vec<MemoryNode>,
+}
+
+enum NodeKind {
+    Element {
+        tag: String,
+        attributes: Vec<(String, String)>,
+    },
+    Text(String),
+    Comment(String),
+}
+
+impl MemoryNode {
+    pub fn element(tag: &str) -> Self {
+        Self::new(NodeKind::Element {
+            tag: tag.to_ascii_lowercase(),
+            attributes: Vec::new(),
+        })
+    }
+
+    pub fn text(value: &str) -> Self {
+        Self::new(NodeKind::Text(value.to_string()))
+    }
+
+    pub fn comment(value: &str) -> Self {
+        Self::new(NodeKind::Comment(value.to_string()))
+    }
+
+    fn new(kind: NodeKind) -> Self {
+        Self(Rc::new(RefCell::new(NodeData {
+            kind,
+            parent: Weak::new(),
+            children: Vec::new(),
+        })))
+    }
+
+    pub fn is_text(&self) -> bool {
+        matches!(self.0.borrow().kind, NodeKind::Text(_))
+    }
+
+    /// Lowercase tag name. None for Text Nodes and Comments.
+    pub fn tag_name(&self) -> Option<String> {
+        match &self.0.borrow().kind {
+            NodeKind::Element { tag, .. } => Some(tag.clone()),
+            NodeKind::Text(_) | NodeKind::Comment(_) => None,
+        }
+    }
+
+    /// The text of a Text Node or Comment. Empty for Elements.
+    pub fn data(&self) -> String {
+        match &self.0.borrow().kind {
+            NodeKind::Text(value) | NodeKind::Comment(value) => value.clone(),
+            NodeKind::Element { .. } => String::new(),
+        }
+    }
+
+    fn set_data(&self, value: String) {
+        if let NodeKind::Text(data) = &mut self.0.borrow_mut().kind {
+            *data = value;
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        match &self.0.borrow().kind {
+            NodeKind::Element { attributes, .. } => attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone()),
+            NodeKind::Text(_) | NodeKind::Comment(_) => None,
+        }
+    }
+
+    pub fn set_attribute(&self, name: &str, value: &str) {
+        if let NodeKind::Element { attributes, .. } = &mut self.0.borrow_mut().kind {
+            match attributes.iter_mut().find(|(key, _)| key == name) {
+                Some((_, current)) => *current = value.to_string(),
+                None => attributes.push((name.to_string(), value.to_string())),
+            }
+        }
+    }
+
+    pub fn remove_attribute(&self, name: &str) {
+        if let NodeKind::Element { attributes, .. } = &mut self.0.borrow_mut().kind {
+            attributes.retain(|(key, _)| key != name);
+        }
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        self.0.borrow().parent.upgrade().map(Self)
+    }
+
+    pub fn children(&self) -> Vec<Self> {
+        self.0.borrow().children.clone()
+    }
+
+    /// True if the Node is us or one of our descendants.
+    pub fn contains(&self, other: &Self) -> bool {
+        let mut current = Some(other.clone());
+
+        while let Some(node) = current {
+            if &node == self {
+                return true;
+            }
+
+            current = node.parent();
+        }
+
+        false
+    }
+
+    /// Moves the Node to the end of our children.
+    pub fn append_child(&self, child: &Self) {
+        child.detach();
+
+        child.0.borrow_mut().parent = Rc::downgrade(&self.0);
+        self.0.borrow_mut().children.push(child.clone());
+    }
+
+    /// Every Text Node inside of us in document order.
+    pub fn text_nodes(&self) -> Vec<Self> {
+        let mut found = Vec::new();
+
+        for child in self.children() {
+            if child.is_text() {
+                found.push(child.clone());
+            }
+
+            found.append(&mut child.text_nodes());
+        }
+
+        found
+    }
+
+    pub fn text_content(&self) -> String {
+        match &self.0.borrow().kind {
+            NodeKind::Text(value) => value.clone(),
+            NodeKind::Comment(_) => String::new(),
+            NodeKind::Element { .. } => self.children().iter().map(Self::text_content).collect(),
+        }
+    }
+
+    /// Serializes our children the same way the browsers' `innerHTML` does.
+    pub fn inner_html(&self) -> String {
+        let mut output = String::new();
+
+        for child in self.children() {
+            child.write_html(&mut output);
+        }
+
+        output
+    }
+
+    pub fn outer_html(&self) -> String {
+        let mut output = String::new();
+        self.write_html(&mut output);
+        output
+    }
+
+    fn write_html(&self, output: &mut String) {
+        let tag = match &self.0.borrow().kind {
+            NodeKind::Text(value) => {
+                let is_raw = self
+                    .parent()
+                    .and_then(|v| v.tag_name())
+                    .is_some_and(|v| RAW_TEXT_ELEMENTS.contains(&v.as_str()));
+
+                if is_raw {
+                    output.push_str(value);
+                } else {
+                    output.push_str(&escape(value, false));
+                }
+
+                return;
+            }
+            NodeKind::Comment(value) => {
+                output.push_str(&format!("<!--{value}-->"));
+                return;
+            }
+            NodeKind::Element { tag, attributes } => {
+                output.push('<');
+                output.push_str(tag);
+
+                for (key, value) in attributes {
+                    output.push_str(&format!(" {key}=\"{}\"", escape(value, true)));
+                }
+
+                output.push('>');
+
+                tag.clone()
+            }
+        };
+
+        if VOID_ELEMENTS.contains(&tag.as_str()) {
+            return;
+        }
+
+        output.push_str(&self.inner_html());
+        output.push_str(&format!("</{tag}>"));
+    }
+
+    fn index_in_parent(&self) -> Option<(Self, usize)> {
+        let parent = self.parent()?;
+        let index = parent.0.borrow().children.iter().position(|v| v == self)?;
+
+        Some((parent, index))
+    }
+
+    fn insert_into(&self, parent: &Self, index: usize) {
+        self.detach();
+
+        self.0.borrow_mut().parent = Rc::downgrade(&parent.0);
+        parent.0.borrow_mut().children.insert(index, self.clone());
+    }
+
+    fn detach(&self) {
+        if let Some((parent, index)) = self.index_in_parent() {
+            parent.0.borrow_mut().children.remove(index);
+        }
+
+        self.0.borrow_mut().parent = Weak::new();
+    }
+}
+
+#[cfg(feature = "render")]
+impl MemoryNode {
+    /// Parses the HTML the same way setting the `innerHTML` of a `div` in the browser does.
+    ///
+    /// Returns the `div` containing the parsed Nodes.
+    pub fn parse_fragment(html: &str) -> Self {
+        use html5ever::{local_name, namespace_url, ns, tendril::TendrilSink, QualName};
+        use markup5ever_rcdom::RcDom;
+
+        let dom = html5ever::parse_fragment(
+            RcDom::default(),
+            Default::default(),
+            QualName::new(None, ns!(html), local_name!("div")),
+            Vec::new(),
+        )
+        .one(html);
+
+        let root = Self::element("div");
+
+        // The parsed Nodes are placed inside of an `html` Element.
+        for fragment in dom.document.children.borrow().iter() {
+            for child in fragment.children.borrow().iter() {
+                root.append_parsed(child);
+            }
+        }
+
+        root
+    }
+
+    fn append_parsed(&self, handle: &markup5ever_rcdom::Handle) {
+        use markup5ever_rcdom::NodeData;
+
+        let node = match &handle.data {
+            NodeData::Text { contents } => Self::text(&contents.borrow()),
+            NodeData::Comment { contents } => Self::comment(contents),
+            // Contents of a `template` aren't its' children so they're skipped.
+            NodeData::Element { name, attrs, .. } => {
+                let attributes = attrs
+                    .borrow()
+                    .iter()
+                    .map(|attr| {
+                        let key = match &attr.name.prefix {
+                            Some(prefix) => format!("{prefix}:{}", attr.name.local),
+                            None => attr.name.local.to_string(),
+                        };
+
+                        (key, attr.value.to_string())
+                    })
+                    .collect();
+
+                // Tag names of SVG Elements aren't lowercase.
+                let element = Self::new(NodeKind::Element {
+                    tag: name.local.to_string(),
+                    attributes,
+                });
+
+                for child in handle.children.borrow().iter() {
+                    element.append_parsed(child);
+                }
+
+                element
+            }
+            _ => return,
+        };
+
+        self.append_child(&node);
+    }
+}
+
+impl PartialEq for MemoryNode {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for MemoryNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0.borrow().kind {
+            NodeKind::Text(value) => f.debug_tuple("Text").field(value).finish(),
+            NodeKind::Comment(value) => f.debug_tuple("Comment").field(value).finish(),
+            NodeKind::Element { tag, .. } => f.debug_tuple("Element").field(tag).finish(),
+        }
+    }
+}
+
+impl Dom for MemoryDom {
+    type Text = MemoryNode;
+    type Element = MemoryNode;
+    type Error = MemoryDomError;
+
+    fn text_data(text: &MemoryNode) -> String {
+        text.data()
+    }
+
+    fn text_length(text: &MemoryNode) -> u32 {
+        text.data().encode_utf16().count() as u32
+    }
+
+    fn split_text(text: &MemoryNode, offset: u32) -> Result<MemoryNode, MemoryDomError> {
+        let units = text.data().encode_utf16().collect::<Vec<_>>();
+
+        if offset as usize > units.len() {
+            return Err(MemoryDomError::IndexSize {
+                offset,
+                length: units.len() as u32,
+            });
+        }
+
+        let (left, right) = units.split_at(offset as usize);
+
+        text.set_data(String::from_utf16_lossy(left));
+
+        let split = MemoryNode::text(&String::from_utf16_lossy(right));
+
+        if let Some((parent, index)) = text.index_in_parent() {
+            split.insert_into(&parent, index + 1);
+        }
+
+        Ok(split)
+    }
+
+    fn append_data(text: &MemoryNode, value: &str) -> Result<(), MemoryDomError> {
+        text.set_data(text.data() + value);
+        Ok(())
+    }
+
+    fn create_span(_near: &MemoryNode) -> Result<MemoryNode, MemoryDomError> {
+        Ok(MemoryNode::element("span"))
+    }
+
+    fn set_class_name(element: &MemoryNode, value: &str) {
+        element.set_attribute("class", value);
+    }
+
+    fn has_attribute(element: &MemoryNode, name: &str) -> bool {
+        element.attribute(name).is_some()
+    }
+
+    fn set_attribute(element: &MemoryNode, name: &str, value: &str) -> Result<(), MemoryDomError> {
+        element.set_attribute(name, value);
+        Ok(())
+    }
+
+    fn remove_attribute(element: &MemoryNode, name: &str) -> Result<(), MemoryDomError> {
+        element.remove_attribute(name);
+        Ok(())
+    }
+
+    fn wrap_text(element: &MemoryNode, text: &MemoryNode) -> Result<(), MemoryDomError> {
+        if let Some((parent, index)) = text.index_in_parent() {
+            element.insert_into(&parent, index);
+        }
+
+        element.append_child(text);
+
+        Ok(())
+    }
+
+    fn replace_with_text(element: &MemoryNode, text: &MemoryNode) -> Result<(), MemoryDomError> {
+        // Detached first since the Text Node is usually inside of the Element.
+        text.detach();
+
+        if let Some((parent, index)) = element.index_in_parent() {
+            element.detach();
+            text.insert_into(&parent, index);
+        }
+
+        Ok(())
+    }
+
+    fn insert_text_after(element: &MemoryNode, text: &MemoryNode) -> Result<(), MemoryDomError> {
+        if element.parent().is_some() {
+            text.detach();
+
+            let (parent, index) = element.index_in_parent().unwrap();
+            text.insert_into(&parent, index + 1);
+        }
+
+        Ok(())
+    }
+
+    fn text_parent(text: &MemoryNode) -> Option<MemoryNode> {
+        text.parent()
+    }
+
+    fn element_parent(element: &MemoryNode) -> Option<MemoryNode> {
+        element.parent()
+    }
+
+    fn remove_text(text: &MemoryNode) {
+        text.detach();
+    }
+
+    fn remove_element(element: &MemoryNode) {
+        element.detach();
+    }
+}
+
+fn escape(value: &str, is_attribute: bool) -> String {
+    let mut output = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '\u{A0}' => output.push_str("&nbsp;"),
+            '"' if is_attribute => output.push_str("&quot;"),
+            '<' if !is_attribute => output.push_str("&lt;"),
+            '>' if !is_attribute => output.push_str("&gt;"),
+            c => output.push(c),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_and_wrap() {
+        let root = MemoryNode::element("p");
+        let text = MemoryNode::text("Hello <world>");
+        root.append_child(&text);
+
+        let split = MemoryDom::split_text(&text, 6).unwrap();
+        assert_eq!(root.children(), [text.clone(), split.clone()]);
+
+        let span = MemoryDom::create_span(&split).unwrap();
+        MemoryDom::set_class_name(&span, "a");
+        MemoryDom::wrap_text(&span, &split).unwrap();
+
+        assert_eq!(
+            root.outer_html(),
+            "<p>Hello <span class=\"a\">&lt;world&gt;</span></p>"
+        );
+
+        MemoryDom::replace_with_text(&span, &split).unwrap();
+        assert_eq!(root.inner_html(), "Hello &lt;world&gt;");
+        assert!(span.parent().is_none());
+
+        assert_eq!(
+            MemoryDom::split_text(&text, 7),
+            Err(MemoryDomError::IndexSize {
+                offset: 7,
+                length: 6
+            })
+        );
+    }
+
+    #[test]
+    fn split_utf16() {
+        let text = MemoryNode::text("a😀b");
+
+        assert_eq!(MemoryDom::text_length(&text), 4);
+
+        let split = MemoryDom::split_text(&text, 3).unwrap();
+        assert_eq!((text.data(), split.data()), ("a😀".into(), "b".into()));
+    }
+
+    #[test]
+    fn detached_after() {
+        // Like the browser, inserting after an Element without a parent does nothing.
+        let span = MemoryNode::element("span");
+        let text = MemoryNode::text("a");
+
+        MemoryDom::insert_text_after(&span, &text).unwrap();
+        assert!(text.parent().is_none());
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn parse_fragment() {
+        let html = "<p>a&nbsp;b &lt;c&gt;<br>d<!-- x --></p><style>p > b {}</style>\
+                    <svg viewBox=\"0 0 1 1\"><foreignObject></foreignObject></svg>";
+
+        let root = MemoryNode::parse_fragment(html);
+
+        assert_eq!(root.inner_html(), html);
+        assert_eq!(
+            root.text_nodes()
+                .iter()
+                .map(MemoryNode::data)
+                .collect::<Vec<_>>(),
+            ["a\u{A0}b <c>", "d", "p > b {}"]
+        );
+
+        // Missing end tags are added like in the browser.
+        let root = MemoryNode::parse_fragment("<ul><li>One<li>Two</ul>");
+        assert_eq!(root.inner_html(), "<ul><li>One</li><li>Two</li></ul>");
+    }
+}
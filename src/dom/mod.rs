@@ -0,0 +1,65 @@
+//! The DOM operations the text layer needs.
+//!
+//! The browser uses [`WebDom`] while [`MemoryDom`] is a pure Rust tree. It allows splitting and
+//! wrapping text without a browser, e.g. in tests or on a server.
+
+mod memory;
+#[cfg(feature = "frontend")]
+mod web;
+
+pub use memory::*;
+#[cfg(feature = "frontend")]
+pub use web::*;
+
+/// The DOM used when none is given. The browsers' if it's available.
+#[cfg(feature = "frontend")]
+pub type DefaultDom = WebDom;
+#[cfg(not(feature = "frontend"))]
+pub type DefaultDom = MemoryDom;
+
+/// Operations on Text Nodes and the Elements wrapping them.
+///
+/// Nodes are handles. Cloning one points to the same Node and equality compares identity.
+/// Offsets and lengths are in UTF-16 code units like in the browser.
+pub trait Dom: 'static {
+    type Text: Clone + PartialEq + std::fmt::Debug;
+    type Element: Clone + PartialEq + std::fmt::Debug;
+    type Error: std::fmt::Debug;
+
+    fn text_data(text: &Self::Text) -> String;
+
+    fn text_length(text: &Self::Text) -> u32;
+
+    /// Splits the Text Node at the offset. The Node after the offset is inserted after it.
+    fn split_text(text: &Self::Text, offset: u32) -> Result<Self::Text, Self::Error>;
+
+    fn append_data(text: &Self::Text, value: &str) -> Result<(), Self::Error>;
+
+    /// Creates a detached span in the document of the Text Node.
+    fn create_span(near: &Self::Text) -> Result<Self::Element, Self::Error>;
+
+    fn set_class_name(element: &Self::Element, value: &str);
+
+    fn has_attribute(element: &Self::Element, name: &str) -> bool;
+
+    fn set_attribute(element: &Self::Element, name: &str, value: &str) -> Result<(), Self::Error>;
+
+    fn remove_attribute(element: &Self::Element, name: &str) -> Result<(), Self::Error>;
+
+    /// Places the Element where the Text Node is and moves the Text Node inside of it.
+    fn wrap_text(element: &Self::Element, text: &Self::Text) -> Result<(), Self::Error>;
+
+    /// Replaces the Element with the Text Node.
+    fn replace_with_text(element: &Self::Element, text: &Self::Text) -> Result<(), Self::Error>;
+
+    /// Inserts the Text Node after the Element. Does nothing if the Element has no parent.
+    fn insert_text_after(element: &Self::Element, text: &Self::Text) -> Result<(), Self::Error>;
+
+    fn text_parent(text: &Self::Text) -> Option<Self::Element>;
+
+    fn element_parent(element: &Self::Element) -> Option<Self::Element>;
+
+    fn remove_text(text: &Self::Text);
+
+    fn remove_element(element: &Self::Element);
+}
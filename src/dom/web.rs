@@ -0,0 +1,80 @@
+use wasm_bindgen::{JsValue, UnwrapThrowExt};
+use web_sys::{Element, Text};
+
+use super::Dom;
+
+/// The browsers' DOM.
+pub struct WebDom;
+
+impl Dom for WebDom {
+    type Text = Text;
+    type Element = Element;
+    type Error = JsValue;
+
+    fn text_data(text: &Text) -> String {
+        text.data()
+    }
+
+    fn text_length(text: &Text) -> u32 {
+        text.length()
+    }
+
+    fn split_text(text: &Text, offset: u32) -> Result<Text, JsValue> {
+        text.split_text(offset)
+    }
+
+    fn append_data(text: &Text, value: &str) -> Result<(), JsValue> {
+        text.append_data(value)
+    }
+
+    fn create_span(near: &Text) -> Result<Element, JsValue> {
+        near.owner_document().unwrap_throw().create_element("span")
+    }
+
+    fn set_class_name(element: &Element, value: &str) {
+        element.set_class_name(value);
+    }
+
+    fn has_attribute(element: &Element, name: &str) -> bool {
+        element.has_attribute(name)
+    }
+
+    fn set_attribute(element: &Element, name: &str, value: &str) -> Result<(), JsValue> {
+        element.set_attribute(name, value)
+    }
+
+    fn remove_attribute(element: &Element, name: &str) -> Result<(), JsValue> {
+        element.remove_attribute(name)
+    }
+
+    fn wrap_text(element: &Element, text: &Text) -> Result<(), JsValue> {
+        text.before_with_node_1(element)?;
+        element.append_child(text)?;
+
+        Ok(())
+    }
+
+    fn replace_with_text(element: &Element, text: &Text) -> Result<(), JsValue> {
+        element.replace_with_with_node_1(text)
+    }
+
+    fn insert_text_after(element: &Element, text: &Text) -> Result<(), JsValue> {
+        element.after_with_node_1(text)
+    }
+
+    fn text_parent(text: &Text) -> Option<Element> {
+        text.parent_element()
+    }
+
+    fn element_parent(element: &Element) -> Option<Element> {
+        element.parent_element()
+    }
+
+    fn remove_text(text: &Text) {
+        text.remove();
+    }
+
+    fn remove_element(element: &Element) {
+        element.remove();
+    }
+}
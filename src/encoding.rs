@@ -0,0 +1,341 @@
+//! The binary format of a [`SaveState`](crate::SaveState).
+//!
+//! Every blob starts with a header:
+//!
+//! | Bytes | Value                                                 |
+//! |-------|-------------------------------------------------------|
+//! | 4     | Magic `EDSS`                                          |
+//! | 1     | Format version. Currently `2`                         |
+//! | 1     | Flags. Bit 0 set if the blob uses [`Encoding::Compact`] |
+//!
+//! After the header every integer is either big endian with a fixed width or a LEB128 varint,
+//! depending on the [`Encoding`]. Indexes are always written as `u64` so blobs are the same on
+//! 32 and 64-bit platforms. Strings are their byte length followed by the UTF-8 bytes.
+//!
+//! Blobs written before the header existed are read with [`Reader::legacy`].
+//!
+//! Format version 2 writes the number of flags of a flagged section as a `u32` instead of a `u8`.
+
+use bytes::Buf;
+
+use crate::{store::DecodeError, ComponentFlag};
+
+pub(crate) const MAGIC: [u8; 4] = *b"EDSS";
+
+/// The version of the binary layout. Unrelated to the `SaveState` version.
+pub(crate) const FORMAT_VERSION: u8 = 2;
+
+const FLAG_COMPACT: u8 = 0b0000_0001;
+
+/// How the integers of the binary format are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Big endian with a fixed width. `u32` takes 4 bytes while `u64` and indexes take 8.
+    #[default]
+    Fixed,
+    /// Every integer is a LEB128 varint. Smaller since most offsets, lengths and ids are small.
+    Compact,
+}
+
+pub(crate) struct Writer {
+    bytes: Vec<u8>,
+    encoding: Encoding,
+}
+
+impl Writer {
+    /// Writer without a header. Used for single nodes.
+    pub fn new(encoding: Encoding) -> Self {
+        Self {
+            bytes: Vec::new(),
+            encoding,
+        }
+    }
+
+    pub fn with_header(encoding: Encoding) -> Self {
+        let mut this = Self::new(encoding);
+
+        this.bytes.extend_from_slice(&MAGIC);
+        this.bytes.push(FORMAT_VERSION);
+        this.bytes.push(match encoding {
+            Encoding::Fixed => 0,
+            Encoding::Compact => FLAG_COMPACT,
+        });
+
+        this
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        match self.encoding {
+            Encoding::Fixed => self.bytes.extend_from_slice(&value.to_be_bytes()),
+            Encoding::Compact => self.varint(value as u64),
+        }
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        match self.encoding {
+            Encoding::Fixed => self.bytes.extend_from_slice(&value.to_be_bytes()),
+            Encoding::Compact => self.varint(value),
+        }
+    }
+
+    pub fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    pub fn flag(&mut self, value: ComponentFlag) {
+        self.u32(value.bits());
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                self.bytes.push(byte);
+                break;
+            }
+
+            self.bytes.push(byte | 0x80);
+        }
+    }
+}
+
+pub(crate) struct Reader<'a, B: Buf> {
+    bytes: &'a mut B,
+    encoding: Encoding,
+    /// Format version of the blob. 0 for blobs without a header.
+    format: u8,
+    /// Width of indexes. 4 bytes in blobs written by the old wasm32 encoder.
+    usize_width: usize,
+}
+
+impl<'a, B: Buf> Reader<'a, B> {
+    /// Reader without a header. Used for single nodes.
+    pub fn new(bytes: &'a mut B, encoding: Encoding) -> Self {
+        Self {
+            bytes,
+            encoding,
+            format: FORMAT_VERSION,
+            usize_width: 8,
+        }
+    }
+
+    /// Reads the header. Returns None without consuming anything if the blob doesn't have one.
+    pub fn with_header(bytes: &'a mut B) -> Result<Option<Self>, DecodeError> {
+        if bytes.chunk().len() < MAGIC.len() || bytes.chunk()[..MAGIC.len()] != MAGIC {
+            return Ok(None);
+        }
+
+        bytes.advance(MAGIC.len());
+
+        let mut this = Self::new(bytes, Encoding::Fixed);
+
+        let version = this.u8()?;
+
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedFormat(version));
+        }
+
+        this.format = version;
+
+        let flags = this.u8()?;
+
+        if flags & !FLAG_COMPACT != 0 {
+            return Err(DecodeError::InvalidTag(flags));
+        }
+
+        if flags & FLAG_COMPACT != 0 {
+            this.encoding = Encoding::Compact;
+        }
+
+        Ok(Some(this))
+    }
+
+    /// Reader for blobs written before the header was added.
+    ///
+    /// Those wrote indexes with the platforms' `usize` width. 4 bytes on wasm32, 8 on 64-bit.
+    pub fn legacy(bytes: &'a mut B, usize_width: usize) -> Self {
+        Self {
+            bytes,
+            encoding: Encoding::Fixed,
+            format: 0,
+            usize_width,
+        }
+    }
+
+    pub fn format(&self) -> u8 {
+        self.format
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.remaining()
+    }
+
+    pub fn u8(&mut self) -> Result<u8, DecodeError> {
+        self.ensure_remaining(1)?;
+        Ok(self.bytes.get_u8())
+    }
+
+    pub fn u32(&mut self) -> Result<u32, DecodeError> {
+        match self.encoding {
+            Encoding::Fixed => {
+                self.ensure_remaining(4)?;
+                Ok(self.bytes.get_u32())
+            }
+            Encoding::Compact => {
+                let value = self.varint()?;
+
+                u32::try_from(value).map_err(|_| DecodeError::Overflow(value))
+            }
+        }
+    }
+
+    pub fn u64(&mut self) -> Result<u64, DecodeError> {
+        match self.encoding {
+            Encoding::Fixed => {
+                self.ensure_remaining(8)?;
+                Ok(self.bytes.get_u64())
+            }
+            Encoding::Compact => self.varint(),
+        }
+    }
+
+    pub fn usize(&mut self) -> Result<usize, DecodeError> {
+        let value = if self.usize_width == 4 && self.encoding == Encoding::Fixed {
+            self.u32()? as u64
+        } else {
+            self.u64()?
+        };
+
+        usize::try_from(value).map_err(|_| DecodeError::Overflow(value))
+    }
+
+    pub fn flag(&mut self) -> Result<ComponentFlag, DecodeError> {
+        let bits = self.u32()?;
+
+        ComponentFlag::from_bits(bits).ok_or(DecodeError::UnknownFlag(bits))
+    }
+
+    pub fn string(&mut self) -> Result<String, DecodeError> {
+        let len = self.u32()? as usize;
+
+        self.ensure_remaining(len)?;
+
+        Ok(String::from_utf8(self.bytes.copy_to_bytes(len).to_vec())?)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            let bits = (byte & 0x7F) as u64;
+
+            // The last byte may only contain the one remaining bit.
+            if shift == 63 && bits > 1 {
+                return Err(DecodeError::InvalidVarint);
+            }
+
+            value |= bits << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(DecodeError::InvalidVarint)
+    }
+
+    fn ensure_remaining(&self, needed: usize) -> Result<(), DecodeError> {
+        if self.bytes.remaining() < needed {
+            Err(DecodeError::Truncated {
+                needed,
+                remaining: self.bytes.remaining(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut writer = Writer::new(Encoding::Compact);
+            writer.u64(value);
+
+            let mut bytes = Bytes::from(writer.into_bytes());
+            let mut reader = Reader::new(&mut bytes, Encoding::Compact);
+
+            assert_eq!(reader.u64(), Ok(value));
+            assert_eq!(reader.remaining(), 0);
+        }
+
+        // 300 = 0b10_0101100
+        let mut writer = Writer::new(Encoding::Compact);
+        writer.u32(300);
+        assert_eq!(writer.into_bytes(), [0b1010_1100, 0b0000_0010]);
+    }
+
+    #[test]
+    fn varint_invalid() {
+        let mut bytes = Bytes::from(vec![0xFF; 11]);
+        assert_eq!(
+            Reader::new(&mut bytes, Encoding::Compact).u64(),
+            Err(DecodeError::InvalidVarint)
+        );
+
+        let mut writer = Writer::new(Encoding::Compact);
+        writer.u64(u32::MAX as u64 + 1);
+
+        let mut bytes = Bytes::from(writer.into_bytes());
+        assert_eq!(
+            Reader::new(&mut bytes, Encoding::Compact).u32(),
+            Err(DecodeError::Overflow(u32::MAX as u64 + 1))
+        );
+    }
+
+    #[test]
+    fn header() {
+        let bytes = Writer::with_header(Encoding::Compact).into_bytes();
+        assert_eq!(
+            bytes,
+            [b'E', b'D', b'S', b'S', FORMAT_VERSION, FLAG_COMPACT]
+        );
+
+        let mut bytes = Bytes::from(bytes);
+        let reader = Reader::with_header(&mut bytes).unwrap().unwrap();
+        assert_eq!(reader.encoding, Encoding::Compact);
+
+        let mut bytes = Bytes::from(vec![b'E', b'D', b'S', b'S', 9, 0]);
+        assert!(matches!(
+            Reader::with_header(&mut bytes),
+            Err(DecodeError::UnsupportedFormat(9))
+        ));
+
+        // Old blobs start with the version.
+        let mut bytes = Bytes::from(vec![0, 0, 0, 2]);
+        assert!(Reader::with_header(&mut bytes).unwrap().is_none());
+        assert_eq!(bytes.len(), 4);
+    }
+}
@@ -0,0 +1,250 @@
+use std::{cell::RefCell, ops::Range, rc::Rc};
+
+use crate::{
+    component::{AnnotationId, ComponentDataStore, FlagsWithData},
+    ComponentFlag, ListenerId,
+};
+
+/// Called for every [`EditorEvent`] of the listener it's subscribed to.
+pub type ListenerEvent = Rc<RefCell<dyn Fn(ListenerId, &EditorEvent)>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorEvent {
+    AnnotationAdded(Annotation),
+    AnnotationRemoved(Annotation),
+    /// The range or data of the annotation changed.
+    AnnotationUpdated(Annotation),
+    /// Emitted once after every change, after the annotation events. Save the state here.
+    Changed,
+    SelectionChanged,
+    ToolbarOpened,
+    ToolbarClosed,
+    /// An annotation of the Component was clicked.
+    ComponentClicked(ComponentFlag),
+}
+
+/// Continuous text using a Component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub flag: ComponentFlag,
+    pub data: Option<u32>,
+    /// Stable id of the data. Unlike `data` it doesn't change when other data is removed.
+    pub id: Option<AnnotationId>,
+    /// UTF-16 offsets into the text of the listener Element.
+    pub range: Range<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(usize);
+
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    next_id: usize,
+    items: Vec<(SubscriptionId, ListenerEvent)>,
+}
+
+impl Subscribers {
+    pub fn add(&mut self, func: ListenerEvent) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+
+        self.next_id += 1;
+        self.items.push((id, func));
+
+        id
+    }
+
+    pub fn remove(&mut self, id: SubscriptionId) -> bool {
+        let len = self.items.len();
+
+        self.items.retain(|(v, _)| *v != id);
+
+        len != self.items.len()
+    }
+
+    pub fn all(&self) -> Vec<ListenerEvent> {
+        self.items.iter().map(|(_, func)| func.clone()).collect()
+    }
+}
+
+/// An annotation along with its' Component data. Used to find updated data.
+pub(crate) type TrackedAnnotation = (Annotation, Option<ComponentDataStore>);
+
+/// Joins the flags of consecutive Text pieces into annotations.
+///
+/// Each piece is the UTF-16 length of the text and its' flags.
+pub(crate) fn collect_annotations<'a>(
+    pieces: impl IntoIterator<Item = (u32, &'a FlagsWithData)>,
+) -> Vec<Annotation> {
+    let mut found = Vec::new();
+    let mut open = Vec::<Annotation>::new();
+    let mut offset = 0;
+
+    for (length, flags) in pieces {
+        let keys = annotation_keys(flags);
+
+        // Close the annotations which don't continue into this piece.
+        let (kept, closed): (Vec<_>, Vec<_>) = open
+            .into_iter()
+            .partition(|v| keys.contains(&(v.flag, v.data)));
+
+        found.extend(closed);
+        open = kept;
+
+        for (flag, data) in keys {
+            if !open.iter().any(|v| v.flag == flag && v.data == data) {
+                open.push(Annotation {
+                    flag,
+                    data,
+                    // Set by the listener which knows the data.
+                    id: None,
+                    range: offset..offset,
+                });
+            }
+        }
+
+        offset += length;
+
+        for annotation in &mut open {
+            annotation.range.end = offset;
+        }
+    }
+
+    found.extend(open);
+    found.sort_by_key(|v| (v.range.start, v.range.end, v.flag.bits(), v.data));
+
+    found
+}
+
+/// Every single flag with its' data id.
+fn annotation_keys(flags: &FlagsWithData) -> Vec<(ComponentFlag, Option<u32>)> {
+    flags
+        .flag
+        .separate_bits()
+        .into_iter()
+        .flat_map(|flag| {
+            let data = flags
+                .data
+                .iter()
+                .filter(|(v, _)| *v == flag)
+                .map(|&(_, id)| Some(id))
+                .collect::<Vec<_>>();
+
+            if data.is_empty() {
+                vec![(flag, None)]
+            } else {
+                data.into_iter().map(|id| (flag, id)).collect()
+            }
+        })
+        .collect()
+}
+
+/// Compares the annotations from before and after a change.
+///
+/// Changed annotations overlapping one from before with the same flag are reported as updated.
+pub(crate) fn diff_annotations(
+    before: &[TrackedAnnotation],
+    after: &[TrackedAnnotation],
+) -> Vec<EditorEvent> {
+    let mut removed = before
+        .iter()
+        .filter(|v| !after.contains(v))
+        .collect::<Vec<_>>();
+
+    let mut updated = Vec::new();
+    let mut added = Vec::new();
+
+    for (annotation, _) in after.iter().filter(|v| !before.contains(v)) {
+        // The same stored data is always the same annotation, even if its' data index moved.
+        let previous = removed
+            .iter()
+            .position(|(prev, _)| prev.id.is_some() && prev.id == annotation.id)
+            .or_else(|| {
+                removed.iter().position(|(prev, _)| {
+                    prev.flag == annotation.flag
+                        && prev.range.start < annotation.range.end
+                        && annotation.range.start < prev.range.end
+                })
+            });
+
+        if let Some(index) = previous {
+            removed.remove(index);
+            updated.push(EditorEvent::AnnotationUpdated(annotation.clone()));
+        } else {
+            added.push(EditorEvent::AnnotationAdded(annotation.clone()));
+        }
+    }
+
+    removed
+        .into_iter()
+        .map(|(v, _)| EditorEvent::AnnotationRemoved(v.clone()))
+        .chain(updated)
+        .chain(added)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotation(flag: ComponentFlag, data: Option<u32>, range: Range<u32>) -> Annotation {
+        Annotation {
+            flag,
+            data,
+            id: None,
+            range,
+        }
+    }
+
+    #[test]
+    fn collect() {
+        let mut both = FlagsWithData::new_with_data(ComponentFlag::HIGHLIGHT, 1);
+        both.insert(FlagsWithData::new_flag(ComponentFlag::BOLD));
+
+        let pieces = [
+            (5, FlagsWithData::empty()),
+            (3, FlagsWithData::new_flag(ComponentFlag::BOLD)),
+            (4, both),
+            (2, FlagsWithData::new_with_data(ComponentFlag::HIGHLIGHT, 1)),
+            (6, FlagsWithData::empty()),
+            (1, FlagsWithData::new_with_data(ComponentFlag::HIGHLIGHT, 2)),
+        ];
+
+        assert_eq!(
+            collect_annotations(pieces.iter().map(|(len, flags)| (*len, flags))),
+            vec![
+                annotation(ComponentFlag::BOLD, None, 5..12),
+                annotation(ComponentFlag::HIGHLIGHT, Some(1), 8..14),
+                annotation(ComponentFlag::HIGHLIGHT, Some(2), 20..21),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let note = |range: Range<u32>, text: &str| {
+            (
+                annotation(ComponentFlag::NOTE, Some(0), range),
+                Some(ComponentDataStore::new(ComponentFlag::NOTE, &text)),
+            )
+        };
+        let bold = |range: Range<u32>| (annotation(ComponentFlag::BOLD, None, range), None);
+
+        let before = [note(0..5, "a"), bold(10..12)];
+
+        assert_eq!(diff_annotations(&before, &before), Vec::new());
+
+        assert_eq!(
+            diff_annotations(&before, &[note(0..5, "b"), bold(20..22)]),
+            vec![
+                EditorEvent::AnnotationRemoved(bold(10..12).0),
+                EditorEvent::AnnotationUpdated(note(0..5, "b").0),
+                EditorEvent::AnnotationAdded(bold(20..22).0),
+            ]
+        );
+
+        assert_eq!(
+            diff_annotations(&before, &[note(2..5, "a"), bold(10..12)]),
+            vec![EditorEvent::AnnotationUpdated(note(2..5, "a").0)]
+        );
+    }
+}
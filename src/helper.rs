@@ -1,6 +1,9 @@
+#[cfg(feature = "frontend")]
 use wasm_bindgen::JsCast;
-use web_sys::{Element, Event, EventTarget};
+#[cfg(feature = "frontend")]
+use web_sys::{Element, Event, EventTarget, HtmlElement, HtmlInputElement, HtmlTextAreaElement};
 
+#[cfg(feature = "frontend")]
 pub fn parents_contains_class(element: Element, class: &str) -> bool {
     if element.class_list().contains(class) {
         true
@@ -11,6 +14,7 @@ pub fn parents_contains_class(element: Element, class: &str) -> bool {
     }
 }
 
+#[cfg(feature = "frontend")]
 pub fn parents_contains_element(element: &Element, other: &Element) -> bool {
     if element == other {
         true
@@ -21,6 +25,27 @@ pub fn parents_contains_element(element: &Element, other: &Element) -> bool {
     }
 }
 
+/// Returns true if the event is meant for a text input. Those handle their own keys.
+#[cfg(feature = "frontend")]
+pub fn is_text_input_event(event: &Event) -> bool {
+    event.target_dyn_into::<HtmlTextAreaElement>().is_some()
+        || event.target_dyn_into::<HtmlInputElement>().is_some()
+        || event
+            .target_dyn_into::<HtmlElement>()
+            .filter(|v| v.is_content_editable())
+            .is_some()
+}
+
+/// Either `#rgb` or `#rrggbb`. Safe to place inside of a stylesheet.
+pub fn is_hex_color(value: &str) -> bool {
+    let Some(hex) = value.strip_prefix('#') else {
+        return false;
+    };
+
+    matches!(hex.len(), 3 | 6) && hex.chars().all(|v| v.is_ascii_hexdigit())
+}
+
+#[cfg(feature = "frontend")]
 // Copied from YEW
 pub trait TargetCast
 where
@@ -45,4 +70,5 @@ where
     }
 }
 
+#[cfg(feature = "frontend")]
 impl<E: AsRef<Event>> TargetCast for E {}
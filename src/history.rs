@@ -0,0 +1,363 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    component::ComponentDataStore,
+    delta::SaveDelta,
+    listener::ListenerData,
+    store::{SavedNode, SavedNodeFlag},
+    Result,
+};
+
+/// Maximum amount of steps we're able to undo.
+const MAX_STEPS: usize = 100;
+
+/// A reversible change to the `ListenerData`.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// The flags of a `TextContainer` changed.
+    Flags {
+        index: usize,
+        before: Vec<SavedNodeFlag>,
+        after: Vec<SavedNodeFlag>,
+    },
+
+    StoreData {
+        value: ComponentDataStore,
+    },
+
+    UpdateData {
+        index: u32,
+        before: ComponentDataStore,
+        after: ComponentDataStore,
+    },
+
+    RemoveData {
+        index: u32,
+        value: ComponentDataStore,
+    },
+}
+
+impl Operation {
+    fn undo(&self, data: &mut ListenerData) -> Result<()> {
+        match self {
+            Self::Flags {
+                index,
+                before,
+                after,
+            } => {
+                data.changes.node(*index, !after.is_empty());
+                data.nodes[*index].set_saved_flags(before)?
+            }
+
+            Self::StoreData { .. } => {
+                data.data.pop();
+            }
+
+            Self::UpdateData { index, before, .. } => {
+                data.changes.data(*index);
+                data.data[*index as usize] = before.clone();
+            }
+
+            Self::RemoveData { index, value } => data.reinsert_data(*index, value.clone()),
+        }
+
+        Ok(())
+    }
+
+    fn redo(&self, data: &mut ListenerData) -> Result<()> {
+        match self {
+            Self::Flags {
+                index,
+                before,
+                after,
+            } => {
+                data.changes.node(*index, !before.is_empty());
+                data.nodes[*index].set_saved_flags(after)?
+            }
+
+            Self::StoreData { value } => {
+                data.changes.data(data.data.len() as u32);
+                data.data.push(value.clone());
+            }
+
+            Self::UpdateData { index, after, .. } => {
+                data.changes.data(*index);
+                data.data[*index as usize] = after.clone();
+            }
+
+            Self::RemoveData { index, .. } => {
+                data.swap_remove_data(*index);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Operation log used for undo and redo.
+///
+/// Operations are collected until [`History::commit`] is called. Committed operations are undone together.
+#[derive(Debug, Default)]
+pub struct History {
+    pending: Vec<Operation>,
+
+    undo: Vec<Vec<Operation>>,
+    redo: Vec<Vec<Operation>>,
+}
+
+impl History {
+    pub fn push(&mut self, operation: Operation) {
+        self.pending.push(operation);
+    }
+
+    /// Groups the pending operations into a single undo step. Returns false if nothing was pending.
+    pub fn commit(&mut self) -> bool {
+        if self.pending.is_empty() {
+            return false;
+        }
+
+        self.undo.push(std::mem::take(&mut self.pending));
+        self.redo.clear();
+
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.pending.is_empty() || !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+/// Node and data indexes written since the last revision. Used to create the revision deltas.
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// Node index and whether the node had flags at the last revision.
+    nodes: BTreeMap<usize, bool>,
+    data: BTreeSet<u32>,
+    /// Length of the data at the last revision.
+    data_len: usize,
+}
+
+impl Changes {
+    pub fn new(data_len: usize) -> Self {
+        Self {
+            data_len,
+            ..Self::default()
+        }
+    }
+
+    /// Only the first call per revision is kept since it has the flags of the last revision.
+    pub fn node(&mut self, index: usize, had_flags: bool) {
+        self.nodes.entry(index).or_insert(had_flags);
+    }
+
+    pub fn data(&mut self, index: u32) {
+        self.data.insert(index);
+    }
+
+    /// Returns the changes between the last revision and the current state.
+    pub fn into_delta(self, data: &ListenerData) -> SaveDelta {
+        let mut delta = SaveDelta::default();
+
+        for (index, had_flags) in self.nodes {
+            let node = &data.nodes[index];
+
+            match (had_flags, node.are_all_flags_empty()) {
+                (true, true) => delta.removed_nodes.push(index),
+                (true, false) => delta
+                    .changed_nodes
+                    .push(SavedNode::from_node(index, &node.text)),
+                (false, false) => delta
+                    .added_nodes
+                    .push(SavedNode::from_node(index, &node.text)),
+                (false, true) => (),
+            }
+        }
+
+        let len = data.data.len();
+
+        delta.changed_data = self
+            .data
+            .into_iter()
+            .filter(|&index| (index as usize) < len.min(self.data_len))
+            .map(|index| (index, data.data[index as usize].clone()))
+            .collect();
+
+        delta.added_data = (self.data_len..len)
+            .map(|index| (index as u32, data.data[index].clone()))
+            .collect();
+
+        delta.removed_data = (len..self.data_len).map(|v| v as u32).collect();
+
+        delta
+    }
+}
+
+/// Reverts the last step. Returns false if there was nothing to undo.
+pub fn undo(data: &mut ListenerData) -> Result<bool> {
+    data.commit();
+
+    let Some(step) = data.history.undo.pop() else {
+        return Ok(false);
+    };
+
+    for operation in step.iter().rev() {
+        operation.undo(data)?;
+    }
+
+    data.refresh_styles();
+    data.next_revision();
+
+    data.history.redo.push(step);
+
+    Ok(true)
+}
+
+/// Re-applies the last undone step. Returns false if there was nothing to redo.
+pub fn redo(data: &mut ListenerData) -> Result<bool> {
+    data.commit();
+
+    let Some(step) = data.history.redo.pop() else {
+        return Ok(false);
+    };
+
+    for operation in &step {
+        operation.redo(data)?;
+    }
+
+    data.refresh_styles();
+    data.next_revision();
+
+    data.history.undo.push(step);
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{store, ComponentFlag, ListenerId};
+
+    use super::*;
+
+    fn notes(data: &ListenerData) -> Vec<String> {
+        data.data.iter().map(|v| v.parse()).collect()
+    }
+
+    #[test]
+    fn undo_redo_data() {
+        let mut data =
+            ListenerData::new(ListenerId::unset(), Vec::new(), Default::default()).unwrap();
+
+        data.store_data(ComponentFlag::NOTE, &"a");
+        data.store_data(ComponentFlag::NOTE, &"b");
+        data.history.commit();
+
+        data.update_data(ComponentFlag::NOTE, 1, &"c");
+        data.history.commit();
+
+        data.store_data(ComponentFlag::NOTE, &"d");
+        data.remove_data(ComponentFlag::NOTE, 0);
+        data.history.commit();
+
+        assert_eq!(notes(&data), ["d", "c"]);
+
+        assert!(undo(&mut data).unwrap());
+        assert_eq!(notes(&data), ["a", "c"]);
+
+        assert!(undo(&mut data).unwrap());
+        assert_eq!(notes(&data), ["a", "b"]);
+
+        assert!(redo(&mut data).unwrap());
+        assert!(redo(&mut data).unwrap());
+        assert!(!redo(&mut data).unwrap());
+        assert_eq!(notes(&data), ["d", "c"]);
+    }
+
+    #[test]
+    fn new_changes_clear_redo() {
+        let mut data =
+            ListenerData::new(ListenerId::unset(), Vec::new(), Default::default()).unwrap();
+
+        data.store_data(ComponentFlag::NOTE, &"a");
+
+        assert!(undo(&mut data).unwrap());
+        assert!(data.history.can_redo());
+
+        data.store_data(ComponentFlag::NOTE, &"b");
+
+        assert!(!redo(&mut data).unwrap());
+        assert_eq!(notes(&data), ["b"]);
+    }
+
+    #[test]
+    fn revisions() {
+        let mut data =
+            ListenerData::new(ListenerId::unset(), Vec::new(), Default::default()).unwrap();
+        data.store_revision();
+
+        data.store_data(ComponentFlag::NOTE, &"a");
+        data.commit();
+        assert_eq!(data.revision(), 1);
+
+        // Nothing changed
+        data.commit();
+        assert_eq!(data.revision(), 1);
+
+        assert!(undo(&mut data).unwrap());
+        assert_eq!(data.revision(), 2);
+
+        let delta = data.save_delta(1).unwrap();
+        assert_eq!((delta.from_revision, delta.to_revision), (1, 2));
+        assert_eq!(delta.removed_data, [0]);
+
+        assert!(data.save_delta(0).unwrap().is_empty());
+        assert_eq!(data.save_delta(5), None);
+    }
+
+    #[test]
+    fn revision_deltas() {
+        let mut data =
+            ListenerData::new(ListenerId::unset(), Vec::new(), Default::default()).unwrap();
+        let mut saves = vec![store::save(&data)];
+
+        data.store_data(ComponentFlag::NOTE, &"a");
+        data.store_data(ComponentFlag::NOTE, &"b");
+        data.store_data(ComponentFlag::NOTE, &"c");
+        data.commit();
+        saves.push(store::save(&data));
+
+        // Moves "c" into the removed position and adds "d" where it was.
+        data.remove_data(ComponentFlag::NOTE, 0);
+        data.store_data(ComponentFlag::NOTE, &"d");
+        data.commit();
+        saves.push(store::save(&data));
+
+        data.update_data(ComponentFlag::NOTE, 1, &"e");
+        data.commit();
+        saves.push(store::save(&data));
+
+        assert!(undo(&mut data).unwrap());
+        saves.push(store::save(&data));
+
+        assert!(undo(&mut data).unwrap());
+        saves.push(store::save(&data));
+
+        assert_eq!(notes(&data), ["a", "b", "c"]);
+
+        for (since, save) in saves.iter().enumerate() {
+            let mut applied = save.clone();
+            applied
+                .apply_delta(&data.save_delta(since as u64).unwrap())
+                .unwrap();
+
+            assert_eq!(applied, store::save(&data));
+        }
+    }
+}
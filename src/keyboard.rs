@@ -0,0 +1,67 @@
+use crate::ComponentFlag;
+
+/// Keys which apply a Component to the selected text.
+///
+/// By default H highlights and N adds a note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcuts {
+    keys: Vec<(String, ComponentFlag)>,
+}
+
+impl Shortcuts {
+    pub fn empty() -> Self {
+        Self { keys: Vec::new() }
+    }
+
+    /// Uses the key for the Component. Replaces the previous Component using the key.
+    ///
+    /// The key is compared against `KeyboardEvent.key` ignoring case.
+    pub fn set(mut self, key: &str, flag: ComponentFlag) -> Self {
+        self.keys.retain(|(v, _)| !v.eq_ignore_ascii_case(key));
+        self.keys.push((key.to_string(), flag));
+        self
+    }
+
+    pub fn remove(mut self, key: &str) -> Self {
+        self.keys.retain(|(v, _)| !v.eq_ignore_ascii_case(key));
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<ComponentFlag> {
+        self.keys
+            .iter()
+            .find(|(v, _)| v.eq_ignore_ascii_case(key))
+            .map(|&(_, flag)| flag)
+    }
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self::empty()
+            .set("h", ComponentFlag::HIGHLIGHT)
+            .set("n", ComponentFlag::NOTE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcut_keys() {
+        let shortcuts = Shortcuts::default();
+
+        assert_eq!(shortcuts.get("H"), Some(ComponentFlag::HIGHLIGHT));
+        assert_eq!(shortcuts.get("n"), Some(ComponentFlag::NOTE));
+        assert_eq!(shortcuts.get("b"), None);
+
+        let shortcuts = shortcuts
+            .set("N", ComponentFlag::BOLD)
+            .set("u", ComponentFlag::UNDERLINE)
+            .remove("h");
+
+        assert_eq!(shortcuts.get("n"), Some(ComponentFlag::BOLD));
+        assert_eq!(shortcuts.get("u"), Some(ComponentFlag::UNDERLINE));
+        assert_eq!(shortcuts.get("h"), None);
+    }
+}
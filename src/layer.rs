@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{helper::is_hex_color, SaveState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LayerId(pub(crate) u32);
+
+impl LayerId {
+    pub(crate) fn class_name(self) -> String {
+        format!("editor-layer-{}", self.0)
+    }
+}
+
+/// Annotations of another user shown on top of the listeners' own.
+///
+/// Layers are read-only. Their text is wrapped with the `editor-layer` class along with
+/// `editor-layer-{id}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationLayer {
+    pub name: String,
+    /// Id of the user who made the annotations.
+    pub owner: String,
+    /// Hex background color of the annotations. Either `#rgb` or `#rrggbb`.
+    pub color: Option<String>,
+    pub visible: bool,
+    pub state: SaveState,
+}
+
+impl AnnotationLayer {
+    pub fn new(name: impl Into<String>, owner: impl Into<String>, state: SaveState) -> Self {
+        Self {
+            name: name.into(),
+            owner: owner.into(),
+            color: None,
+            visible: true,
+            state,
+        }
+    }
+
+    pub fn with_color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    /// CSS rule coloring the text of the layer. None if it has no valid color or is hidden.
+    pub fn stylesheet(&self, id: LayerId) -> Option<String> {
+        let color = self.color.as_deref().filter(|_| self.visible)?;
+
+        if !is_hex_color(color) {
+            warn!("Invalid Layer Color {color:?}");
+            return None;
+        }
+
+        Some(format!(
+            ".{} {{ background-color: {color}; }}",
+            id.class_name()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::migration::CURRENT_VERSION;
+
+    use super::*;
+
+    #[test]
+    fn layer_stylesheet() {
+        let state = SaveState {
+            version: CURRENT_VERSION,
+            data: Vec::new(),
+            nodes: Vec::new(),
+        };
+
+        let mut layer = AnnotationLayer::new("Book Club", "reader-1", state).with_color("#8ecae6");
+
+        assert_eq!(
+            layer.stylesheet(LayerId(2)).as_deref(),
+            Some(".editor-layer-2 { background-color: #8ecae6; }")
+        );
+
+        layer.visible = false;
+        assert_eq!(layer.stylesheet(LayerId(2)), None);
+
+        layer.visible = true;
+        layer.color = Some(String::from("red; } body { display: none"));
+        assert_eq!(layer.stylesheet(LayerId(2)), None);
+    }
+}
@@ -1,28 +1,80 @@
 #[macro_use]
 extern crate log;
 
+#[cfg(feature = "frontend")]
 use wasm_bindgen::JsValue;
 
+mod anchor;
 pub mod component;
+mod delta;
+#[cfg(feature = "frontend")]
 mod document;
+mod dom;
+mod encoding;
+#[cfg(feature = "frontend")]
+mod event;
+#[cfg(feature = "frontend")]
 mod gui;
 mod helper;
+#[cfg(feature = "frontend")]
+mod history;
+#[cfg(feature = "frontend")]
+mod keyboard;
+mod layer;
+#[cfg(feature = "frontend")]
 mod listener;
+mod markdown;
 mod migration;
+#[cfg(feature = "frontend")]
+mod note_view;
+#[cfg(feature = "render")]
+mod render;
+#[cfg(feature = "frontend")]
+mod segment;
+#[cfg(feature = "frontend")]
 mod selection;
 mod store;
 mod text;
+#[cfg(feature = "frontend")]
 mod toolbar;
+#[cfg(feature = "frontend")]
+mod touch;
+#[cfg(feature = "frontend")]
 mod util;
 
+#[cfg(feature = "frontend")]
 pub type Result<V, E = JsValue> = std::result::Result<V, E>;
 
-pub use component::{Component, ComponentFlag};
-pub use listener::{
-    register, register_with_data, ListenerEvent, ListenerHandle, ListenerId, MouseListener,
-};
-pub use store::{load_and_register, save, SaveState, SavedNode, SavedNodeFlag};
+pub use anchor::{ContainerHint, TextAnchor, TextPositionSelector, TextQuoteSelector};
+pub use component::{AnnotationId, Component, ComponentFlag, ComponentRegistry};
+pub use delta::{DeltaError, SaveDelta};
+pub use dom::{Dom, MemoryDom, MemoryDomError, MemoryNode};
+pub use encoding::Encoding;
+pub use layer::{AnnotationLayer, LayerId};
+pub use migration::MigrationError;
+pub use store::{DecodeError, SaveState, SavedNode, SavedNodeFlag};
 pub use text::{TextContainer, WrappedText};
+
+#[cfg(feature = "frontend")]
+pub use dom::WebDom;
+#[cfg(feature = "frontend")]
+pub use event::{Annotation, EditorEvent, ListenerEvent, SubscriptionId};
+#[cfg(feature = "frontend")]
+pub use keyboard::Shortcuts;
+#[cfg(feature = "frontend")]
+pub use listener::{register, register_with_data, ListenerHandle, ListenerId, MouseListener};
+#[cfg(feature = "frontend")]
+pub use note_view::{GutterSide, NoteViewConfig};
+#[cfg(feature = "render")]
+pub use render::{render_html, RenderError};
+#[cfg(feature = "frontend")]
+pub use segment::{Granularity, UnicodeWords, WordBoundary};
+#[cfg(feature = "frontend")]
+pub use store::{load_and_register, save, save_anchored};
+#[cfg(feature = "frontend")]
+pub use toolbar::ToolbarConfig;
+#[cfg(feature = "frontend")]
 pub use util::{LinePoint, RangeBox};
 
+#[cfg(feature = "frontend")]
 pub(crate) use listener::SharedListenerData;
@@ -1,5 +1,6 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    collections::VecDeque,
     rc::{Rc, Weak},
     sync::atomic::{AtomicUsize, Ordering},
 };
@@ -7,22 +8,42 @@ use std::{
 use lazy_static::lazy_static;
 use serde::Serialize;
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue, UnwrapThrowExt};
-use web_sys::{Document, Element, HtmlElement, MouseEvent, Node, Range, Selection, Text};
+use web_sys::{
+    Document, Element, Event, HtmlElement, KeyboardEvent, MouseEvent, Node, Selection, Text,
+};
 
 use crate::{
-    component::{ComponentDataStore, Context, FlagsWithData},
+    component::{
+        AnnotationId, Component, ComponentData, ComponentDataStore, ComponentRegistry,
+        FlagsWithData, Highlight, HighlightColor, HighlightTypes,
+    },
+    delta::SaveDelta,
     document,
-    helper::{parents_contains_class, TargetCast},
-    selection, store,
+    event::{
+        collect_annotations, diff_annotations, Annotation, EditorEvent, ListenerEvent, Subscribers,
+        SubscriptionId, TrackedAnnotation,
+    },
+    helper::{is_text_input_event, parents_contains_class, TargetCast},
+    history::{self, Changes, History, Operation},
+    keyboard::Shortcuts,
+    layer::{AnnotationLayer, LayerId},
+    migration::CURRENT_VERSION,
+    note_view::{register_note_view_events, NoteView, NoteViewConfig},
+    segment::{Granularity, TextRun, UnicodeWords, WordBoundary},
+    selection,
+    store::{self, SaveState, SavedNodeFlag},
     text::{return_all_text_nodes, FoundWrappedTextRefMut, TextContentWithFlag},
-    toolbar::Toolbar,
-    util::{ElementEvent, LinePoint, RangeBox},
-    Component, ComponentFlag, Result, TextContainer, WrappedText,
+    toolbar::{Toolbar, ToolbarConfig},
+    touch,
+    util::{ElementEvent, RangeBox},
+    ComponentFlag, Result, TextContainer, WrappedText,
 };
 
+/// How many revisions are kept to create deltas from.
+const MAX_REVISIONS: usize = 100;
+
 pub type SharedListenerType = Rc<RefCell<Listener>>;
 pub type SharedListenerData = Weak<RefCell<ListenerData>>;
-pub type ListenerEvent = Rc<RefCell<dyn Fn(ListenerId)>>;
 // TODO: Fix. I don't like all these Rc's
 
 lazy_static! {
@@ -30,12 +51,14 @@ lazy_static! {
 }
 
 thread_local! {
-    static LISTENERS: RefCell<Vec<SharedListenerType>> = RefCell::new(Vec::new());
+    static LISTENERS: RefCell<Vec<SharedListenerType>> = const { RefCell::new(Vec::new()) };
 }
 
 #[derive(PartialEq, Eq)]
 pub enum MouseListener {
     All,
+    /// Same as `All`. Also handles long-press word selection and selection handles for touch devices.
+    Touch,
     Ignore,
 }
 
@@ -81,6 +104,30 @@ impl ListenerId {
 
         Some(save).filter(|v| !v.nodes.is_empty())
     }
+
+    /// Returns None if Listener was not found OR if the revision is no longer kept.
+    pub fn try_save_delta(&self, since: u64) -> Option<SaveDelta> {
+        let listener = self.try_get()?;
+
+        let borrow = listener.borrow();
+
+        let borrow2 = borrow.data.borrow();
+
+        borrow2.save_delta(since)
+    }
+
+    /// Same as [`ListenerId::try_save`] but also stores a [`crate::TextAnchor`] for every node.
+    pub fn try_save_anchored(&self) -> Option<store::SaveState> {
+        let listener = self.try_get()?;
+
+        let borrow = listener.borrow();
+
+        let borrow2 = borrow.data.borrow();
+
+        let save = store::save_anchored(&borrow2, &borrow.element);
+
+        Some(save).filter(|v| !v.nodes.is_empty())
+    }
 }
 
 impl std::ops::Deref for ListenerId {
@@ -95,24 +142,427 @@ impl std::ops::Deref for ListenerId {
 pub struct ListenerData {
     pub(crate) listener_id: ListenerId,
 
+    /// The Components which can be used.
+    pub(crate) components: Rc<ComponentRegistry>,
+
     /// Specific Data stored for Components.
     pub(crate) data: Vec<ComponentDataStore>,
+    /// The highest [`AnnotationId`] given to the data.
+    pub(crate) last_annotation_id: u64,
     /// The Text Nodes inside the listener Element. Along with flags for the Text.
     pub(crate) nodes: Vec<TextContainer>,
+
+    /// Saved nodes we were unable to find when loading.
+    pub(crate) unplaced: Vec<store::SavedNode>,
+
+    pub(crate) history: History,
+
+    /// Stylesheet generated from the Components' data.
+    pub(crate) styles: Option<Element>,
+
+    /// Annotations of other users.
+    pub(crate) layers: Vec<(LayerId, AnnotationLayer)>,
+
+    /// Increased for every committed change.
+    pub(crate) revision: u64,
+    /// The revision and state of the oldest kept revision.
+    pub(crate) base: (u64, store::SaveState),
+    /// The changes of every revision after the base. Used to create deltas.
+    pub(crate) revisions: VecDeque<SaveDelta>,
+    /// Written since the last revision.
+    pub(crate) changes: Changes,
 }
 
 impl ListenerData {
-    pub fn new(listener_id: ListenerId, nodes: Vec<Text>) -> Result<Self> {
+    pub fn new(
+        listener_id: ListenerId,
+        nodes: Vec<Text>,
+        components: Rc<ComponentRegistry>,
+    ) -> Result<Self> {
         Ok(Self {
             listener_id,
             data: Vec::new(),
+            last_annotation_id: 0,
             nodes: nodes
                 .into_iter()
-                .map(TextContainer::new)
+                .map(|text| TextContainer::new(text, components.clone()))
                 .collect::<Result<_>>()?,
+            components,
+            unplaced: Vec::new(),
+            history: History::default(),
+            styles: None,
+            layers: Vec::new(),
+            revision: 0,
+            base: (
+                0,
+                SaveState {
+                    version: CURRENT_VERSION,
+                    data: Vec::new(),
+                    nodes: Vec::new(),
+                },
+            ),
+            revisions: VecDeque::new(),
+            changes: Changes::default(),
+        })
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Commits the pending changes to the history. Starts a new revision if anything changed.
+    pub(crate) fn commit(&mut self) {
+        if self.history.commit() {
+            self.next_revision();
+        }
+    }
+
+    pub(crate) fn next_revision(&mut self) {
+        let changes = std::mem::replace(&mut self.changes, Changes::new(self.data.len()));
+
+        let mut delta = changes.into_delta(self);
+
+        delta.from_revision = self.revision;
+        self.revision += 1;
+        delta.to_revision = self.revision;
+
+        self.revisions.push_back(delta);
+
+        if self.revisions.len() > MAX_REVISIONS {
+            if let Some(oldest) = self.revisions.pop_front() {
+                if let Err(e) = self.base.1.apply_delta(&oldest) {
+                    warn!("Unable to apply revision {}: {e}", oldest.to_revision);
+                }
+
+                self.base.0 = oldest.to_revision;
+            }
+        }
+    }
+
+    /// Uses the current state as the base to create deltas from. Drops the older revisions.
+    pub(crate) fn store_revision(&mut self) {
+        self.base = (self.revision, store::save(self));
+        self.revisions.clear();
+        self.changes = Changes::new(self.data.len());
+    }
+
+    /// Returns the changes since the revision. None if the revision is too old.
+    pub fn save_delta(&self, since: u64) -> Option<SaveDelta> {
+        if since < self.base.0 || since > self.revision {
+            return None;
+        }
+
+        let mut before = self.base.1.clone();
+
+        let (older, newer): (Vec<_>, Vec<_>) =
+            self.revisions.iter().partition(|v| v.to_revision <= since);
+
+        for delta in older {
+            before.apply_delta(delta).ok()?;
+        }
+
+        let mut after = before.clone();
+
+        for delta in newer {
+            after.apply_delta(delta).ok()?;
+        }
+
+        let mut delta = SaveDelta::between(&before, &after);
+
+        delta.from_revision = since;
+        delta.to_revision = self.revision;
+
+        Some(delta)
+    }
+
+    /// Regenerates the stylesheet from the Components' data and the layers.
+    pub(crate) fn refresh_styles(&self) {
+        if let Some(styles) = self.styles.as_ref() {
+            let mut value = self.components.stylesheet(&self.data);
+
+            for (id, layer) in &self.layers {
+                if let Some(rule) = layer.stylesheet(*id) {
+                    value += "\n";
+                    value += &rule;
+                }
+            }
+
+            styles.set_text_content(Some(&value));
+        }
+    }
+
+    /// Adds the layer and shows it if it's visible.
+    pub fn add_layer(
+        &mut self,
+        mut layer: AnnotationLayer,
+        container: &Element,
+    ) -> Result<LayerId> {
+        layer.state.migrate()?;
+
+        let id = LayerId(
+            self.layers
+                .iter()
+                .map(|(id, _)| id.0 + 1)
+                .max()
+                .unwrap_or_default(),
+        );
+
+        self.layers.push((id, layer));
+
+        self.render_layer(id, container)?;
+        self.refresh_styles();
+
+        Ok(id)
+    }
+
+    /// Returns false if the layer doesn't exist.
+    pub fn remove_layer(&mut self, id: LayerId) -> Result<bool> {
+        let Some(index) = self.layers.iter().position(|(v, _)| *v == id) else {
+            return Ok(false);
+        };
+
+        self.layers.remove(index);
+
+        for node in &mut self.nodes {
+            node.set_layer_flags(id, &[])?;
+        }
+
+        self.refresh_styles();
+
+        Ok(true)
+    }
+
+    /// Hides or shows the layer. The layers' state is kept either way.
+    pub fn set_layer_visible(
+        &mut self,
+        id: LayerId,
+        visible: bool,
+        container: &Element,
+    ) -> Result<bool> {
+        let Some((_, layer)) = self.layers.iter_mut().find(|(v, _)| *v == id) else {
+            return Ok(false);
+        };
+
+        layer.visible = visible;
+
+        self.render_layer(id, container)?;
+        self.refresh_styles();
+
+        Ok(true)
+    }
+
+    /// Replaces the state of the layer. e.g. after another user made changes.
+    pub fn set_layer_state(
+        &mut self,
+        id: LayerId,
+        mut state: SaveState,
+        container: &Element,
+    ) -> Result<bool> {
+        let Some((_, layer)) = self.layers.iter_mut().find(|(v, _)| *v == id) else {
+            return Ok(false);
+        };
+
+        state.migrate()?;
+        layer.state = state;
+
+        self.render_layer(id, container)?;
+
+        Ok(true)
+    }
+
+    /// Merges the flags of the layer into the Text Containers. Removes them if it's hidden.
+    fn render_layer(&mut self, id: LayerId, container: &Element) -> Result<()> {
+        let Some((_, layer)) = self.layers.iter().find(|(v, _)| *v == id) else {
+            return Ok(());
+        };
+
+        let mut flags = vec![Vec::new(); self.nodes.len()];
+
+        if layer.visible {
+            let texts = self
+                .nodes
+                .iter()
+                .map(TextContainer::text_content)
+                .collect::<Vec<_>>();
+            let first_nodes = self
+                .nodes
+                .iter()
+                .map(|v| v.text[0].node.clone())
+                .collect::<Vec<_>>();
+
+            let (placed, unplaced) =
+                store::place_saved_nodes(layer.state.nodes.clone(), &texts, |hint| {
+                    store::nodes_in_hint(container, &first_nodes, hint)
+                });
+
+            if !unplaced.is_empty() {
+                warn!(
+                    "Unable to place {} saved nodes of layer {:?}",
+                    unplaced.len(),
+                    layer.name
+                );
+            }
+
+            for saved in placed {
+                flags[saved.index()].extend_from_slice(saved.flags());
+            }
+        }
+
+        for (node, mut flags) in self.nodes.iter_mut().zip(flags) {
+            flags.sort_by_key(|v| v.offset);
+
+            node.set_layer_flags(id, &flags)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the user defined Highlight colors along with their data id.
+    pub fn highlight_colors(&self) -> Vec<(u32, HighlightColor)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.0 == Highlight::FLAG)
+            .filter_map(|(i, v)| {
+                Some((Highlight::data_id_for_index(i as u32), v.try_parse().ok()?))
+            })
+            .collect()
+    }
+
+    /// Removes the user defined Highlight color. Text using it falls back to the default color.
+    pub fn remove_highlight_color(&mut self, data_index: u32) {
+        let snapshot = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, cont)| (index, cont.saved_flags()))
+            .collect();
+
+        let id = Highlight::data_id_for_index(data_index);
+        let default = Highlight::get_default_data_id();
+
+        for node in &mut self.nodes {
+            for text in &mut node.text {
+                text.change_flags_data(Highlight::FLAG, id, default);
+            }
+        }
+
+        self.record_flag_changes(snapshot);
+
+        self.remove_data(Highlight::FLAG, data_index);
+    }
+
+    /// Returns every annotation in the order they appear.
+    pub fn annotations(&self) -> Vec<Annotation> {
+        self.tracked_annotations()
+            .into_iter()
+            .map(|(annotation, _)| annotation)
+            .collect()
+    }
+
+    pub(crate) fn tracked_annotations(&self) -> Vec<TrackedAnnotation> {
+        collect_annotations(
+            self.nodes
+                .iter()
+                .flat_map(|cont| cont.text.iter())
+                .map(|text| (text.node.length(), &text.flag)),
+        )
+        .into_iter()
+        .map(|mut annotation| {
+            let store = annotation
+                .data
+                .and_then(|id| self.get_data_by_id(annotation.flag, id));
+
+            annotation.id = store.as_ref().map(|v| v.id());
+
+            (annotation, store)
+        })
+        .collect()
+    }
+
+    /// Finds the data using the data id stored in the flags.
+    pub(crate) fn get_data_by_id(
+        &self,
+        flag: ComponentFlag,
+        id: u32,
+    ) -> Option<ComponentDataStore> {
+        let index = self.data_index_for_id(flag, id)?;
+
+        Some(self.data[index as usize].clone())
+    }
+
+    /// Returns the index of the data which the data id stored in the flags points to.
+    pub(crate) fn data_index_for_id(&self, flag: ComponentFlag, id: u32) -> Option<u32> {
+        let component = self.components.get(flag)?;
+
+        (0..self.data.len() as u32).find(|&index| {
+            self.data[index as usize].0 == flag && component.data_id_for_index(index) == id
         })
     }
 
+    /// The Text Nodes the annotation consists of.
+    pub(crate) fn annotation_nodes(&self, annotation: &Annotation) -> Vec<Text> {
+        let flag = annotation_flag(annotation);
+
+        let mut offset = 0;
+        let mut found = Vec::new();
+
+        for text in self.nodes.iter().flat_map(|cont| cont.text.iter()) {
+            let start = offset;
+            offset += text.node.length();
+
+            if start >= annotation.range.start
+                && offset <= annotation.range.end
+                && start != offset
+                && text.has_flag(&flag)
+            {
+                found.push(text.node.clone());
+            }
+        }
+
+        found
+    }
+
+    /// Removes the annotation from its' text.
+    ///
+    /// If `with_data` is true its' stored data is removed too once no other annotation uses it.
+    pub(crate) fn remove_annotation(
+        &mut self,
+        annotation: &Annotation,
+        with_data: bool,
+    ) -> Result<bool> {
+        let nodes = self.annotation_nodes(annotation);
+
+        if nodes.is_empty() {
+            return Ok(false);
+        }
+
+        let flag = annotation_flag(annotation);
+        let snapshot = self.snapshot_flags(&nodes);
+
+        for node in &nodes {
+            self.remove_component_node_flag(node, &flag)?;
+        }
+
+        self.record_flag_changes(snapshot);
+
+        if let Some(id) = annotation.data.filter(|_| with_data) {
+            let is_used = self
+                .annotations()
+                .iter()
+                .any(|v| v.flag == annotation.flag && v.data == Some(id));
+
+            if let Some(index) = self
+                .data_index_for_id(annotation.flag, id)
+                .filter(|_| !is_used)
+            {
+                self.remove_data(annotation.flag, index);
+            }
+        }
+
+        Ok(true)
+    }
+
     // TODO: Put into a better location
     pub fn get_flagged_text(&self) -> Vec<TextContentWithFlag> {
         let mut found = Vec::new();
@@ -149,7 +599,17 @@ impl ListenerData {
     pub fn store_data<S: Serialize>(&mut self, flag: ComponentFlag, data: &S) -> u32 {
         let len = self.data.len() as u32;
 
-        self.data.push(ComponentDataStore::new(flag, data));
+        let mut value = ComponentDataStore::new(flag, data);
+        value.2 = self.next_annotation_id();
+
+        self.history.push(Operation::StoreData {
+            value: value.clone(),
+        });
+        self.changes.data(len);
+
+        self.data.push(value);
+
+        self.refresh_styles();
 
         len
     }
@@ -168,19 +628,144 @@ impl ListenerData {
 
         assert_eq!(data_item.0, flag);
 
-        *data_item = ComponentDataStore::new(flag, data);
+        let before = data_item.clone();
+        data_item.update(data);
+
+        self.history.push(Operation::UpdateData {
+            index: data_index,
+            before,
+            after: data_item.clone(),
+        });
+        self.changes.data(data_index);
+
+        self.refresh_styles();
     }
 
     pub fn remove_data(&mut self, flag: ComponentFlag, data_index: u32) {
+        let value = self.swap_remove_data(data_index);
+
+        assert_eq!(value.0, flag);
+
+        self.history.push(Operation::RemoveData {
+            index: data_index,
+            value,
+        });
+    }
+
+    /// Returns the current index of the data with the stable id.
+    pub fn find_data_index(&self, id: AnnotationId) -> Option<u32> {
+        self.data
+            .iter()
+            .position(|v| v.2 == id)
+            .map(|index| index as u32)
+    }
+
+    fn next_annotation_id(&mut self) -> AnnotationId {
+        self.last_annotation_id += 1;
+        AnnotationId(self.last_annotation_id)
+    }
+
+    /// Gives the loaded data without an id a new one. Continues after the highest loaded id.
+    pub(crate) fn assign_annotation_ids(&mut self) {
+        self.last_annotation_id = self
+            .data
+            .iter()
+            .map(|v| v.2 .0)
+            .max()
+            .unwrap_or_default()
+            .max(self.last_annotation_id);
+
+        for index in 0..self.data.len() {
+            if self.data[index].2.is_unset() {
+                self.data[index].2 = self.next_annotation_id();
+            }
+        }
+    }
+
+    /// Removes the data, moving the last data item into its' position.
+    pub(crate) fn swap_remove_data(&mut self, data_index: u32) -> ComponentDataStore {
         let value = self.data.swap_remove(data_index as usize);
         let last_data_pos = self.data.len() as u32;
 
-        assert_eq!(value.0, flag);
+        self.changes.data(data_index);
+
+        // Update the flags which pointed to the moved data.
+        if let Some(moved) = self.data.get(data_index as usize) {
+            let moved_flag = moved.0;
+
+            self.change_data_index(moved_flag, last_data_pos, data_index);
+        }
+
+        self.refresh_styles();
+
+        value
+    }
+
+    /// Reverses [`ListenerData::swap_remove_data`].
+    pub(crate) fn reinsert_data(&mut self, data_index: u32, value: ComponentDataStore) {
+        self.changes.data(data_index);
+
+        if data_index as usize == self.data.len() {
+            self.data.push(value);
+        } else {
+            self.changes.data(self.data.len() as u32);
+
+            let moved = std::mem::replace(&mut self.data[data_index as usize], value);
+            let moved_flag = moved.0;
+            let last_data_pos = self.data.len() as u32;
+
+            self.data.push(moved);
+
+            self.change_data_index(moved_flag, data_index, last_data_pos);
+        }
+
+        self.refresh_styles();
+    }
+
+    /// Points the flags which referenced the data at `from` to `to`.
+    fn change_data_index(&mut self, flag: ComponentFlag, from: u32, to: u32) {
+        let (from, to) = match self.components.get(flag) {
+            Some(component) => (
+                component.data_id_for_index(from),
+                component.data_id_for_index(to),
+            ),
+            None => (from, to),
+        };
+
+        for (index, node) in self.nodes.iter_mut().enumerate() {
+            if node.text.iter().any(|v| v.intersects_flag(flag)) {
+                self.changes.node(index, true);
+            }
 
-        // TODO: Update other data nodes with new Data Position.
-        for node in &mut self.nodes {
             for text in &mut node.text {
-                text.change_flags_data(flag, last_data_pos, data_index);
+                text.change_flags_data(flag, from, to);
+            }
+        }
+    }
+
+    /// Returns the flags of every `TextContainer` which contains one of the nodes.
+    pub(crate) fn snapshot_flags(&self, nodes: &[Text]) -> Vec<(usize, Vec<SavedNodeFlag>)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, cont)| nodes.iter().any(|node| cont.contains_node(node)))
+            .map(|(index, cont)| (index, cont.saved_flags()))
+            .collect()
+    }
+
+    /// Records the changes made since [`ListenerData::snapshot_flags`] was called.
+    pub(crate) fn record_flag_changes(&mut self, snapshot: Vec<(usize, Vec<SavedNodeFlag>)>) {
+        for (index, before) in snapshot {
+            let after = self.nodes[index].saved_flags();
+
+            if before != after {
+                self.changes.node(index, !before.is_empty());
+
+                self.history.push(Operation::Flags {
+                    index,
+                    before,
+                    after,
+                });
             }
         }
     }
@@ -222,17 +807,34 @@ impl ListenerData {
     }
 }
 
+fn annotation_flag(annotation: &Annotation) -> FlagsWithData {
+    match annotation.data {
+        Some(data) => FlagsWithData::new_with_data(annotation.flag, data),
+        None => FlagsWithData::new_flag(annotation.flag),
+    }
+}
+
 pub struct Listener {
     pub listener_id: ListenerId,
 
-    pub on_event: ListenerEvent,
+    pub(crate) subscribers: Subscribers,
+    /// The annotations when the subscribers were last notified of a change.
+    pub(crate) annotations: Vec<TrackedAnnotation>,
 
     pub element: HtmlElement,
-    functions: Vec<ElementEvent>,
+    pub(crate) functions: Vec<ElementEvent>,
 
     pub data: Rc<RefCell<ListenerData>>,
 
     pub(crate) toolbar: Toolbar,
+
+    /// Tooltips and the gutter of the notes.
+    pub(crate) note_view: NoteView,
+
+    pub(crate) shortcuts: Shortcuts,
+
+    /// Used when selecting a word from a point.
+    pub(crate) word_boundary: Rc<dyn WordBoundary>,
 }
 
 /// Keeps the listener active until we're dropped.
@@ -244,280 +846,327 @@ impl ListenerHandle {
         Self(ListenerId::unset())
     }
 
-    pub fn resize_selection_to(&self, is_start: bool, x: f32, y: f32) -> Result<RangeBox> {
-        let document = self.0.document();
-
+    /// Shows the annotations of another user on top of our own.
+    pub fn add_layer(&self, layer: AnnotationLayer) -> Result<Option<LayerId>> {
         let Some(listener) = self.0.try_get() else {
             warn!("Unable to acquire listener. Does it still exist?");
-            return Ok(RangeBox::default());
+            return Ok(None);
         };
 
-        let Some(selection) = document.get_selection()? else {
-            warn!("Unable to get selection");
-            return Ok(RangeBox::default());
+        let borrow = listener.borrow();
+        let id = borrow.data.borrow_mut().add_layer(layer, &borrow.element)?;
+
+        Ok(Some(id))
+    }
+
+    /// Returns false if the layer doesn't exist.
+    pub fn remove_layer(&self, id: LayerId) -> Result<bool> {
+        let Some(listener) = self.0.try_get() else {
+            warn!("Unable to acquire listener. Does it still exist?");
+            return Ok(false);
         };
 
-        let Some(caret) = document.caret_position_from_point(x, y) else {
-            warn!("Unable to get Caret Position");
-            return Ok(RangeBox::default());
+        let borrow = listener.borrow();
+        let mut data = borrow.data.borrow_mut();
+
+        data.remove_layer(id)
+    }
+
+    /// Hides or shows the layer without losing its' annotations.
+    pub fn set_layer_visible(&self, id: LayerId, visible: bool) -> Result<bool> {
+        let Some(listener) = self.0.try_get() else {
+            warn!("Unable to acquire listener. Does it still exist?");
+            return Ok(false);
         };
 
-        let node_offset = caret.offset();
+        let borrow = listener.borrow();
+        let mut data = borrow.data.borrow_mut();
 
-        let Some(node) = caret.offset_node() else {
-            warn!("Unable to find Caret Offset Node");
-            return Ok(RangeBox::default());
+        data.set_layer_visible(id, visible, &borrow.element)
+    }
+
+    /// Replaces the annotations of the layer.
+    pub fn set_layer_state(&self, id: LayerId, state: SaveState) -> Result<bool> {
+        let Some(listener) = self.0.try_get() else {
+            warn!("Unable to acquire listener. Does it still exist?");
+            return Ok(false);
         };
 
-        let range = selection.get_range_at(0)?;
+        let borrow = listener.borrow();
+        let mut data = borrow.data.borrow_mut();
 
-        if is_start {
-            range.set_start(&node, node_offset)?;
-        } else {
-            range.set_end(&node, node_offset)?;
-        }
+        data.set_layer_state(id, state, &borrow.element)
+    }
 
-        let rect_list = range.get_client_rects().unwrap_throw();
+    pub fn layers(&self) -> Vec<(LayerId, AnnotationLayer)> {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().data.borrow().layers.clone())
+            .unwrap_or_default()
+    }
 
-        if rect_list.length() == 0 {
-            return Ok(RangeBox::default());
-        }
+    /// The current revision. Pass it to [`ListenerId::try_save_delta`] later to sync the changes since.
+    pub fn revision(&self) -> u64 {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().data.borrow().revision())
+            .unwrap_or_default()
+    }
 
-        let start = rect_list.get(0).unwrap_throw();
-        let end = rect_list.get(rect_list.length() - 1).unwrap_throw();
+    /// Calls the function for every event of the listener until unsubscribed.
+    pub fn subscribe(&self, func: ListenerEvent) -> Option<SubscriptionId> {
+        let Some(listener) = self.0.try_get() else {
+            warn!("Unable to acquire listener. Does it still exist?");
+            return None;
+        };
 
-        reposition_toolbar(&listener, selection)?;
+        let id = listener.borrow_mut().subscribers.add(func);
 
-        // TODO: Return new range list?
+        Some(id)
+    }
 
-        Ok(RangeBox {
-            start: LinePoint {
-                x: start.x(),
-                y: start.y(),
-                height: start.height(),
-            },
-            end: LinePoint {
-                x: end.right(),
-                y: end.y(),
-                height: end.height(),
-            },
-        })
+    /// Returns false if the subscription doesn't exist.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.0
+            .try_get()
+            .is_some_and(|v| v.borrow_mut().subscribers.remove(id))
     }
 
-    pub fn has_selection(&self) -> Result<bool> {
-        let document = self.0.document();
+    pub fn annotations(&self) -> Vec<Annotation> {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().data.borrow().annotations())
+            .unwrap_or_default()
+    }
 
-        let Some(selection) = document.get_selection()? else {
-            warn!("Unable to get selection");
-            return Ok(false);
-        };
+    pub fn resize_selection_to(&self, is_start: bool, x: f32, y: f32) -> Result<RangeBox> {
+        resize_selection(self.0, is_start, x, y)
+    }
 
-        Ok(selection.range_count() != 0)
+    pub fn toolbar_config(&self) -> ToolbarConfig {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().toolbar.config.clone())
+            .unwrap_or_default()
     }
 
-    pub fn unselect(&self) -> Result<()> {
-        let Some(listener) = self.0.try_get() else {
+    /// Changes which buttons are shown in the toolbar. Used the next time it's opened.
+    pub fn set_toolbar_config(&self, config: ToolbarConfig) {
+        if let Some(listener) = self.0.try_get() {
+            listener.borrow_mut().toolbar.config = config;
+        } else {
             warn!("Unable to acquire listener. Does it still exist?");
-            return Ok(());
-        };
+        }
+    }
 
-        let document = self.0.document();
+    pub fn note_view_config(&self) -> NoteViewConfig {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().note_view.config)
+            .unwrap_or_default()
+    }
 
-        let Some(selection) = document.get_selection()? else {
-            warn!("Unable to get selection");
+    /// Changes how notes are shown. Re-renders the gutter.
+    pub fn set_note_view_config(&self, config: NoteViewConfig) -> Result<()> {
+        let Some(listener) = self.0.try_get() else {
+            warn!("Unable to acquire listener. Does it still exist?");
             return Ok(());
         };
 
-        close_toolbar(&listener)?;
+        let mut borrow = listener.borrow_mut();
+        let borrow = &mut *borrow;
 
-        selection.remove_all_ranges()?;
+        borrow.note_view.config = config;
 
-        Ok(())
+        let data = borrow.data.borrow();
+        borrow.note_view.refresh(&borrow.element, &data)
     }
 
-    /// Selects a word from the point you clicked and will also call `on_click` for a component.
-    pub fn click_or_select(&self, x: f32, y: f32, target: Element) -> Result<RangeBox> {
+    /// Saved nodes which couldn't be placed when loading.
+    pub fn unplaced_annotations(&self) -> Vec<store::SavedNode> {
         let Some(listener) = self.0.try_get() else {
             warn!("Unable to acquire listener. Does it still exist?");
-            return Ok(RangeBox::default());
+            return Vec::new();
         };
 
-        handle_listener_mouseclick(
-            target,
-            &self.0.to_class_string(),
-            &self.0.document(),
-            &Rc::downgrade(&listener),
-        )?;
+        let borrow = listener.borrow();
+        let data = borrow.data.borrow();
 
-        self.select_word_from_point(x, y, &self.0.document())
+        data.unplaced.clone()
     }
 
-    /// Highlights' the word you clicked and opens the toolbar.
-    fn select_word_from_point(
-        &self,
-        x: f32,
-        y: f32,
-        document: &Document,
-    ) -> Result<RangeBox, JsValue> {
-        fn is_stop_break(value: char) -> bool {
-            [
-                ' ', '.', ',', '?', '!', '"', '*', '(', ')', ';', ':',
-                // Multi-byte characters.
-                '“',
-            ]
-            .contains(&value)
-        }
-
-        let Some(caret) = document.caret_position_from_point(x, y) else {
-            warn!("Unable to get Caret Position");
-            return Ok(RangeBox::default());
-        };
+    /// Reverts the last change. Returns false if there was nothing to undo.
+    pub fn undo(&self) -> Result<bool> {
+        apply_history(self.0, history::undo)
+    }
 
-        let Some(node) = caret.offset_node() else {
-            warn!("Unable to find Caret Offset Node");
-            return Ok(RangeBox::default());
-        };
+    /// Re-applies the last undone change. Returns false if there was nothing to redo.
+    pub fn redo(&self) -> Result<bool> {
+        apply_history(self.0, history::redo)
+    }
 
-        if !parents_contains_class(node.parent_element().unwrap(), &self.0.to_class_string()) {
-            return Ok(RangeBox::default());
+    pub fn can_undo(&self) -> bool {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().data.borrow().history.can_undo())
+            .unwrap_or_default()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().data.borrow().history.can_redo())
+            .unwrap_or_default()
+    }
+
+    /// Changes how words are found when selecting from a point.
+    pub fn set_word_boundary<W: WordBoundary + 'static>(&self, value: W) {
+        if let Some(listener) = self.0.try_get() {
+            listener.borrow_mut().word_boundary = Rc::new(value);
         }
+    }
 
-        let Some(node_value) = node.node_value() else {
-            warn!("Unable to get Node Value");
-            return Ok(RangeBox::default());
+    pub fn shortcuts(&self) -> Shortcuts {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().shortcuts.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_shortcuts(&self, value: Shortcuts) {
+        if let Some(listener) = self.0.try_get() {
+            listener.borrow_mut().shortcuts = value;
+        }
+    }
+
+    /// Returns the user defined Highlight colors along with their data id.
+    pub fn highlight_colors(&self) -> Vec<(u32, HighlightColor)> {
+        self.0
+            .try_get()
+            .map(|v| v.borrow().data.borrow().highlight_colors())
+            .unwrap_or_default()
+    }
+
+    /// Adds a user defined Highlight color. Returns the data id used to highlight with it.
+    pub fn add_highlight_color(&self, color: HighlightColor) -> Result<Option<u32>> {
+        if !color.is_valid() {
+            return Err(JsValue::from_str(&format!(
+                "Invalid Highlight Color {:?}",
+                color.color
+            )));
+        }
+
+        let Some(listener) = self.0.try_get() else {
+            warn!("Unable to acquire listener. Does it still exist?");
+            return Ok(None);
         };
 
-        debug!("Node Value: {}", node_value);
+        let id = {
+            let borrow = listener.borrow();
+            let mut data = borrow.data.borrow_mut();
 
-        let node_offset = caret.offset() as usize;
+            let index = data.store_data(Highlight::FLAG, &color);
+            data.commit();
 
-        let mut start_offset = node_offset;
-        let mut length = node_value.len();
+            Highlight::data_id_for_index(index)
+        };
 
-        debug!(
-            "Cursor Selected {}",
-            &node_value
-                .chars()
-                .skip(start_offset)
-                .take(2)
-                .collect::<String>()
-        );
+        notify_changed(self.0);
+
+        Ok(Some(id))
+    }
+
+    /// Removes a user defined Highlight color. Returns false if the color doesn't exist.
+    pub fn remove_highlight_color(&self, id: u32) -> Result<bool> {
+        let HighlightTypes::Custom(index) = HighlightTypes::from_id(id) else {
+            return Ok(false);
+        };
+
+        let Some(listener) = self.0.try_get() else {
+            warn!("Unable to acquire listener. Does it still exist?");
+            return Ok(false);
+        };
 
-        // TODO: Optimize. We need to check other Nodes.
-        if node_value
-            .chars()
-            .nth(start_offset)
-            .map(is_stop_break)
-            .unwrap_or_default()
         {
-            start_offset += 1;
+            let borrow = listener.borrow();
+            let mut data = borrow.data.borrow_mut();
 
-            for i in 0..length {
-                if let Some(next_byte) = node_value.chars().nth(start_offset + i) {
-                    if is_stop_break(next_byte) {
-                        length = i;
-                        break;
-                    }
-                } else {
-                    break;
-                }
-            }
-        } else {
-            // Backtrack
-            for i in (0..start_offset).rev() {
-                if is_stop_break(node_value.chars().nth(i).unwrap()) {
-                    start_offset = i + 1;
-                    break;
-                } else if i == 0 {
-                    start_offset = 0;
-                }
+            if data
+                .data
+                .get(index as usize)
+                .is_none_or(|v| v.0 != Highlight::FLAG)
+            {
+                return Ok(false);
             }
 
-            // Move until space
-            for i in 0..length {
-                if let Some(next_byte) = node_value.chars().nth(node_offset + i) {
-                    if is_stop_break(next_byte) {
-                        length = node_offset - start_offset + i;
-                        break;
-                    }
-                } else {
-                    break;
-                }
-            }
+            data.remove_highlight_color(index);
+            data.commit();
         }
 
+        close_toolbar(&listener)?;
+
+        notify_changed(self.0);
+
+        Ok(true)
+    }
+
+    pub fn has_selection(&self) -> Result<bool> {
+        let document = self.0.document();
+
         let Some(selection) = document.get_selection()? else {
             warn!("Unable to get selection");
-            return Ok(RangeBox::default());
+            return Ok(false);
         };
 
+        Ok(selection.range_count() != 0)
+    }
+
+    pub fn unselect(&self) -> Result<()> {
         let Some(listener) = self.0.try_get() else {
             warn!("Unable to acquire listener. Does it still exist?");
-            return Ok(RangeBox::default());
+            return Ok(());
         };
 
-        let handler = Rc::downgrade(&listener);
-
-        // TODO: If we disabled events and we double click OOBs this can be called and displays the toolbar.
-        if selection.range_count() != 0 {
-            display_toolbar(&handler)?;
-        }
-
-        if start_offset == start_offset + length {
-            warn!("Unable to find word");
-            return Ok(RangeBox::default());
-        }
+        let document = self.0.document();
 
-        // Check if we're out of bounds
-        if node_value.len() < start_offset + length {
-            warn!(
-                "Invalid Selection: Out of Bounds ({} + {} > {})",
-                start_offset,
-                length,
-                node_value.len()
-            );
-            return Ok(RangeBox::default());
-        }
+        let Some(selection) = document.get_selection()? else {
+            warn!("Unable to get selection");
+            return Ok(());
+        };
 
-        debug!(
-            "Word Selection: {}",
-            node_value
-                .chars()
-                .skip(start_offset)
-                .take(length)
-                .collect::<String>()
-        );
+        close_toolbar(&listener)?;
 
-        // Selection Range Changes
         selection.remove_all_ranges()?;
 
-        let range = Range::new()?;
-
-        range.set_start(&node, start_offset as u32)?;
-        range.set_end(&node, (start_offset + length) as u32)?;
-
-        selection.add_range(&range)?;
+        Ok(())
+    }
 
-        // Open Toolbar
+    /// Selects the text around the point you clicked and will also call `on_click` for a component.
+    pub fn click_or_select(
+        &self,
+        x: f32,
+        y: f32,
+        target: Element,
+        granularity: Granularity,
+    ) -> Result<RangeBox> {
+        let Some(listener) = self.0.try_get() else {
+            warn!("Unable to acquire listener. Does it still exist?");
+            return Ok(RangeBox::default());
+        };
 
-        display_toolbar(&handler)?;
+        handle_listener_mouseclick(
+            target,
+            &self.0.to_class_string(),
+            &self.0.document(),
+            &Rc::downgrade(&listener),
+        )?;
 
-        let ranges = range.get_client_rects().unwrap_throw();
-        let start = ranges.get(0).unwrap_throw();
-        let end = ranges.get(ranges.length() - 1).unwrap_throw();
+        select_from_point(self.0, x, y, granularity, &self.0.document())
+    }
 
-        Ok(RangeBox {
-            start: LinePoint {
-                x: start.x(),
-                y: start.y(),
-                height: start.height(),
-            },
-            end: LinePoint {
-                x: end.right(),
-                y: end.y(),
-                height: end.height(),
-            },
-        })
+    /// Selects the text around the point and opens the toolbar. Returns the selected range.
+    pub fn select_from_point(&self, x: f32, y: f32, granularity: Granularity) -> Result<RangeBox> {
+        select_from_point(self.0, x, y, granularity, &self.0.document())
     }
 }
 
@@ -538,6 +1187,10 @@ impl Drop for ListenerHandle {
             {
                 let listener = listeners.remove(index);
 
+                if let Some(styles) = listener.borrow().data.borrow().styles.as_ref() {
+                    styles.remove();
+                }
+
                 document::delete_document(listener.borrow().listener_id);
 
                 let listener_class = self.0.to_class_string();
@@ -554,6 +1207,129 @@ impl Drop for ListenerHandle {
     }
 }
 
+/// Moves the start or end of the selection to the point.
+pub(crate) fn resize_selection(
+    listener_id: ListenerId,
+    is_start: bool,
+    x: f32,
+    y: f32,
+) -> Result<RangeBox> {
+    let document = listener_id.document();
+
+    let Some(listener) = listener_id.try_get() else {
+        warn!("Unable to acquire listener. Does it still exist?");
+        return Ok(RangeBox::default());
+    };
+
+    let Some(selection) = document.get_selection()? else {
+        warn!("Unable to get selection");
+        return Ok(RangeBox::default());
+    };
+
+    let Some(caret) = document.caret_position_from_point(x, y) else {
+        warn!("Unable to get Caret Position");
+        return Ok(RangeBox::default());
+    };
+
+    let node_offset = caret.offset();
+
+    let Some(node) = caret.offset_node() else {
+        warn!("Unable to find Caret Offset Node");
+        return Ok(RangeBox::default());
+    };
+
+    let range = selection.get_range_at(0)?;
+
+    if is_start {
+        range.set_start(&node, node_offset)?;
+    } else {
+        range.set_end(&node, node_offset)?;
+    }
+
+    reposition_toolbar(&listener, selection)?;
+
+    // TODO: Return new range list?
+
+    Ok(RangeBox::from_range(&range))
+}
+
+/// Highlights' the text around the point and opens the toolbar.
+pub(crate) fn select_from_point(
+    listener_id: ListenerId,
+    x: f32,
+    y: f32,
+    granularity: Granularity,
+    document: &Document,
+) -> Result<RangeBox, JsValue> {
+    let Some(caret) = document.caret_position_from_point(x, y) else {
+        warn!("Unable to get Caret Position");
+        return Ok(RangeBox::default());
+    };
+
+    let Some(node) = caret.offset_node() else {
+        warn!("Unable to find Caret Offset Node");
+        return Ok(RangeBox::default());
+    };
+
+    if node.node_type() != Node::TEXT_NODE
+        || !parents_contains_class(
+            node.parent_element().unwrap(),
+            &listener_id.to_class_string(),
+        )
+    {
+        return Ok(RangeBox::default());
+    }
+
+    let Some(selection) = document.get_selection()? else {
+        warn!("Unable to get selection");
+        return Ok(RangeBox::default());
+    };
+
+    let Some(listener) = listener_id.try_get() else {
+        warn!("Unable to acquire listener. Does it still exist?");
+        return Ok(RangeBox::default());
+    };
+
+    let handler = Rc::downgrade(&listener);
+
+    // TODO: If we disabled events and we double click OOBs this can be called and displays the toolbar.
+    if selection.range_count() != 0 {
+        display_toolbar(&handler)?;
+    }
+
+    let (run, word_boundary) = {
+        let borrow = listener.borrow();
+
+        (
+            TextRun::around(&node, &borrow.element),
+            borrow.word_boundary.clone(),
+        )
+    };
+
+    let Some(found) = run
+        .offset_of(&node, caret.offset())
+        .and_then(|offset| granularity.range_at(&run.text, offset, word_boundary.as_ref()))
+    else {
+        warn!("Unable to find {granularity:?}");
+        return Ok(RangeBox::default());
+    };
+
+    debug!("{granularity:?} Selection: {}", &run.text[found.clone()]);
+
+    // Selection Range Changes
+    selection.remove_all_ranges()?;
+
+    let range = run.create_range(found)?;
+
+    selection.add_range(&range)?;
+
+    // Open Toolbar
+
+    display_toolbar(&handler)?;
+
+    Ok(RangeBox::from_range(&range))
+}
+
 pub fn register_with_data(
     element: HtmlElement,
     mut data: ListenerData,
@@ -569,11 +1345,6 @@ pub fn register_with_data(
             panic!("Already Listening on Element!");
         }
 
-        let on_event = match on_event {
-            Some(v) => v,
-            None => Rc::new(RefCell::new(|_| {})) as ListenerEvent,
-        };
-
         let index = ListenerId(INCREMENT.fetch_add(1, Ordering::Relaxed));
         let listener_class = index.to_class_string();
 
@@ -582,8 +1353,12 @@ pub fn register_with_data(
         }
 
         data.listener_id = index;
+        attach_styles(&mut data)?;
+        data.store_revision();
         let listener_data = Rc::new(RefCell::new(data));
-        let toolbar = Toolbar::new(index, Rc::downgrade(&listener_data), &on_event);
+        let toolbar = Toolbar::new(index, Rc::downgrade(&listener_data));
+
+        let annotations = listener_data.borrow().tracked_annotations();
 
         // Add class to container element
         element.class_list().add_1(&listener_class)?;
@@ -591,19 +1366,34 @@ pub fn register_with_data(
         let listener_rc = Rc::new(RefCell::new(Listener {
             listener_id: index,
 
-            on_event,
+            subscribers: Subscribers::default(),
+            annotations,
 
             element,
             functions: Vec::new(),
             toolbar,
+            note_view: NoteView::default(),
+            shortcuts: Shortcuts::default(),
+            word_boundary: Rc::new(UnicodeWords),
 
             data: listener_data,
         }));
 
-        if listener == MouseListener::All {
+        if listener == MouseListener::Touch {
+            touch::register_touch_events(&listener_rc, listener_class.clone())?;
+        }
+
+        if listener != MouseListener::Ignore {
             register_listener_events(&listener_rc, listener_class)?;
         }
 
+        if let Some(on_event) = on_event {
+            listener_rc.borrow_mut().subscribers.add(on_event);
+        }
+
+        // Loaded notes need their tabindex and gutter before the first change.
+        refresh_note_view(&listener_rc);
+
         listeners.push(listener_rc);
 
         Ok(ListenerHandle(index))
@@ -611,11 +1401,14 @@ pub fn register_with_data(
 }
 
 /// Should be called AFTER page has fully loaded and finished any Element changes.
+///
+/// Uses the built-in Components if `components` is None.
 pub fn register(
     element: HtmlElement,
     listener: MouseListener,
     document: Option<Document>,
     on_event: Option<ListenerEvent>,
+    components: Option<ComponentRegistry>,
 ) -> Result<ListenerHandle> {
     LISTENERS.with(|listeners| -> Result<ListenerHandle> {
         let mut listeners = listeners.borrow_mut();
@@ -625,11 +1418,6 @@ pub fn register(
             panic!("Already Listening on Element!");
         }
 
-        let on_event = match on_event {
-            Some(v) => v,
-            None => Rc::new(RefCell::new(|_| {})) as ListenerEvent,
-        };
-
         let listener_id = ListenerId(INCREMENT.fetch_add(1, Ordering::Relaxed));
         let listener_class = listener_id.to_class_string();
 
@@ -639,8 +1427,15 @@ pub fn register(
 
         let nodes = return_all_text_nodes(&element);
 
-        let listener_data = Rc::new(RefCell::new(ListenerData::new(listener_id, nodes)?));
-        let toolbar = Toolbar::new(listener_id, Rc::downgrade(&listener_data), &on_event);
+        let mut data =
+            ListenerData::new(listener_id, nodes, Rc::new(components.unwrap_or_default()))?;
+        attach_styles(&mut data)?;
+        data.store_revision();
+
+        let listener_data = Rc::new(RefCell::new(data));
+        let toolbar = Toolbar::new(listener_id, Rc::downgrade(&listener_data));
+
+        let annotations = listener_data.borrow().tracked_annotations();
 
         // Add class to container element
         element.class_list().add_1(&listener_class)?;
@@ -648,25 +1443,57 @@ pub fn register(
         let listener_rc = Rc::new(RefCell::new(Listener {
             listener_id,
 
-            on_event,
+            subscribers: Subscribers::default(),
+            annotations,
 
             element,
             functions: Vec::new(),
             toolbar,
+            note_view: NoteView::default(),
+            shortcuts: Shortcuts::default(),
+            word_boundary: Rc::new(UnicodeWords),
 
             data: listener_data,
         }));
 
-        if listener == MouseListener::All {
+        if listener == MouseListener::Touch {
+            touch::register_touch_events(&listener_rc, listener_class.clone())?;
+        }
+
+        if listener != MouseListener::Ignore {
             register_listener_events(&listener_rc, listener_class)?;
         }
 
+        if let Some(on_event) = on_event {
+            listener_rc.borrow_mut().subscribers.add(on_event);
+        }
+
+        // Loaded notes need their tabindex and gutter before the first change.
+        refresh_note_view(&listener_rc);
+
         listeners.push(listener_rc);
 
         Ok(ListenerHandle(listener_id))
     })
 }
 
+/// Adds the stylesheet generated from the Components' data to the document.
+fn attach_styles(data: &mut ListenerData) -> Result<()> {
+    let document = data.listener_id.document();
+
+    let styles = document.create_element("style")?;
+
+    match document.head() {
+        Some(head) => head.append_child(&styles)?,
+        None => document.body().unwrap_throw().append_child(&styles)?,
+    };
+
+    data.styles = Some(styles);
+    data.refresh_styles();
+
+    Ok(())
+}
+
 fn register_listener_events(
     listener_rc: &SharedListenerType,
     listener_class: String,
@@ -719,6 +1546,7 @@ fn register_listener_events(
     // Create the mouse down listener
     {
         let listener_class = listener_class.clone();
+        let is_mouse_down = is_mouse_down.clone();
 
         let function = Closure::wrap(Box::new(move |event: MouseEvent| {
             if parents_contains_class(event.target_unchecked_into(), &listener_class) {
@@ -734,31 +1562,309 @@ fn register_listener_events(
         ));
     }
 
+    register_note_view_events(listener_rc, listener_class.clone())?;
+
     // Create the on click listener
     {
+        let listener_id = listener_rc.borrow().listener_id;
         let document2 = document.clone();
         let listener = Rc::downgrade(listener_rc);
         let function: Closure<dyn FnMut(MouseEvent)> = Closure::new(move |event: MouseEvent| {
-            handle_listener_mouseclick(
-                event.target_unchecked_into(),
-                &listener_class,
-                &document2,
-                &listener,
-            )
-            .unwrap_throw();
+            let target: Element = event.target_unchecked_into();
+
+            // Replace the browsers' own double and triple click selection.
+            if let Some(granularity) = Granularity::from_click_count(event.detail()) {
+                if parents_contains_class(target.clone(), &listener_class) {
+                    select_from_point(
+                        listener_id,
+                        event.client_x() as f32,
+                        event.client_y() as f32,
+                        granularity,
+                        &document2,
+                    )
+                    .unwrap_throw();
+                }
+
+                return;
+            }
+
+            handle_listener_mouseclick(target, &listener_class, &document2, &listener)
+                .unwrap_throw();
         });
 
         listener_rc.borrow_mut().functions.push(ElementEvent::link(
-            document.unchecked_into(),
+            document.clone().unchecked_into(),
             function,
             |t, f| t.add_event_listener_with_callback("click", f),
             Box::new(|t, f| t.remove_event_listener_with_callback("click", f)),
         ));
     }
 
+    // Create the undo/redo key listener
+    {
+        let listener = Rc::downgrade(listener_rc);
+
+        let function: Closure<dyn FnMut(KeyboardEvent)> =
+            Closure::new(move |event: KeyboardEvent| {
+                if !(event.ctrl_key() || event.meta_key()) || !event.key().eq_ignore_ascii_case("z")
+                {
+                    return;
+                }
+
+                // Let text inputs handle their own history.
+                if is_text_input_event(&event) {
+                    return;
+                }
+
+                let Some(listener_rc) = listener.upgrade() else {
+                    return;
+                };
+
+                // Other listeners and the page keep their own history.
+                if !focus_inside(&listener_rc) {
+                    return;
+                }
+
+                let listener_id = listener_rc.borrow().listener_id;
+
+                let func = if event.shift_key() {
+                    history::redo
+                } else {
+                    history::undo
+                };
+
+                // Only take over the browsers' undo if we had anything to undo.
+                match apply_history(listener_id, func) {
+                    Ok(true) => event.prevent_default(),
+                    Ok(false) => (),
+                    Err(e) => error!("Unable to apply history: {e:?}"),
+                }
+            });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.clone().unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback("keydown", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("keydown", f)),
+        ));
+    }
+
+    // Selections made with the keyboard. Opens the toolbar once the key is released.
+    let is_selection_changed = Rc::new(RefCell::new(false));
+
+    // Create the selection change listener
+    {
+        let is_selection_changed = is_selection_changed.clone();
+        let listener = Rc::downgrade(listener_rc);
+        let was_inside = Cell::new(false);
+
+        let function: Closure<dyn FnMut(Event)> = Closure::new(move |_event: Event| {
+            if !*is_mouse_down.borrow() {
+                *is_selection_changed.borrow_mut() = true;
+            }
+
+            let Some(listener_rc) = listener.upgrade() else {
+                return;
+            };
+
+            // Also notify when the selection leaves the listener.
+            let is_inside = selection_inside(&listener_rc).unwrap_throw().is_some();
+            let was_inside = was_inside.replace(is_inside);
+
+            if is_inside || was_inside {
+                let listener_id = listener_rc.borrow().listener_id;
+
+                emit_event(listener_id, EditorEvent::SelectionChanged);
+            }
+        });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.clone().unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback("selectionchange", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("selectionchange", f)),
+        ));
+    }
+
+    // Create the key up listener
+    {
+        let listener = Rc::downgrade(listener_rc);
+
+        let function: Closure<dyn FnMut(KeyboardEvent)> =
+            Closure::new(move |event: KeyboardEvent| {
+                if !is_selection_changed.replace(false) || is_text_input_event(&event) {
+                    return;
+                }
+
+                if let Some(listener_rc) = listener.upgrade() {
+                    if selection_inside(&listener_rc).unwrap_throw().is_some()
+                        || listener_rc.borrow().toolbar.is_open()
+                    {
+                        display_toolbar(&listener).unwrap_throw();
+                    }
+                }
+            });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.clone().unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback("keyup", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("keyup", f)),
+        ));
+    }
+
+    // Create the shortcut key listener
+    {
+        let listener = Rc::downgrade(listener_rc);
+
+        let function: Closure<dyn FnMut(KeyboardEvent)> =
+            Closure::new(move |event: KeyboardEvent| {
+                if event.ctrl_key()
+                    || event.meta_key()
+                    || event.alt_key()
+                    || is_text_input_event(&event)
+                {
+                    return;
+                }
+
+                let Some(listener_rc) = listener.upgrade() else {
+                    return;
+                };
+
+                let Some(selection) = selection_inside(&listener_rc).unwrap_throw() else {
+                    return;
+                };
+
+                if event.key() == "Tab" && !event.shift_key() {
+                    // Move into the toolbar from the selected text.
+                    let borrow = listener_rc.borrow();
+
+                    if !event
+                        .target_dyn_into::<Element>()
+                        .is_some_and(|v| borrow.toolbar.contains(&v))
+                        && borrow.toolbar.focus_button(0)
+                    {
+                        event.prevent_default();
+                    }
+                } else {
+                    let flag = listener_rc.borrow().shortcuts.get(&event.key());
+
+                    if let Some(flag) = flag {
+                        event.prevent_default();
+
+                        activate_component(&listener_rc, flag, selection).unwrap_throw();
+                    }
+                }
+            });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback("keydown", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("keydown", f)),
+        ));
+    }
+
     Ok(())
 }
 
+/// Returns the selection if it isn't collapsed and starts inside the listener Element.
+fn selection_inside(listener: &SharedListenerType) -> Result<Option<Selection>> {
+    let borrow = listener.borrow();
+
+    let Some(selection) = borrow
+        .listener_id
+        .document()
+        .get_selection()?
+        .filter(|v| !v.is_collapsed())
+    else {
+        return Ok(None);
+    };
+
+    let is_inside = selection
+        .anchor_node()
+        .is_some_and(|node| borrow.element.contains(Some(&node)));
+
+    Ok(Some(selection).filter(|_| is_inside))
+}
+
+/// Same as clicking the Components' toolbar button.
+fn activate_component(
+    listener: &SharedListenerType,
+    flag: ComponentFlag,
+    selection: Selection,
+) -> Result<bool> {
+    let (listener_id, data) = {
+        let borrow = listener.borrow();
+        (borrow.listener_id, borrow.data.clone())
+    };
+
+    let Some(component) = data.borrow().components.get(flag).cloned() else {
+        warn!("No Component registered for the shortcut {flag:?}");
+        return Ok(false);
+    };
+
+    let nodes = Rc::new(RefCell::new(selection::get_nodes_in_selection(
+        selection,
+        Rc::downgrade(&data),
+    )?));
+
+    component.on_click_button(nodes, listener_id.document())?;
+
+    data.borrow_mut().commit();
+
+    display_toolbar(&Rc::downgrade(listener))?;
+
+    notify_changed(listener_id);
+
+    Ok(true)
+}
+
+/// Calls undo or redo on the listeners' data. Notifies the listener if anything changed.
+fn apply_history(
+    listener_id: ListenerId,
+    func: fn(&mut ListenerData) -> Result<bool>,
+) -> Result<bool> {
+    let Some(listener) = listener_id.try_get() else {
+        warn!("Unable to acquire listener. Does it still exist?");
+        return Ok(false);
+    };
+
+    let changed = {
+        let borrow = listener.borrow();
+        let mut data = borrow.data.borrow_mut();
+
+        func(&mut data)?
+    };
+
+    if changed {
+        close_toolbar(&listener)?;
+
+        notify_changed(listener_id);
+    }
+
+    Ok(changed)
+}
+
+/// True if the selection or the focused Element is inside the listener Element.
+fn focus_inside(listener: &SharedListenerType) -> bool {
+    let borrow = listener.borrow();
+    let document = borrow.listener_id.document();
+
+    let selection = document
+        .get_selection()
+        .ok()
+        .flatten()
+        .and_then(|v| v.anchor_node());
+
+    let active = document.active_element().map(Node::from);
+
+    [selection, active]
+        .into_iter()
+        .flatten()
+        .any(|node| borrow.element.contains(Some(&node)))
+}
+
 fn handle_listener_mouseclick(
     target: Element,
     listening_class: &str,
@@ -802,56 +1908,119 @@ fn handle_listener_mouseclick(
             selection::create_container(text_nodes, Rc::downgrade(&handle.data)).unwrap_throw(),
         ));
 
-        // TODO: Improve
-        for flag in flags.separate_bits() {
-            match flag {
-                ComponentFlag::ITALICIZE => crate::component::Italicize
-                    .on_click(&Context::new(nodes.clone(), document.clone()))
-                    .unwrap_throw(),
-                ComponentFlag::HIGHLIGHT => crate::component::Highlight
-                    .on_click(&Context::new(nodes.clone(), document.clone()))
-                    .unwrap_throw(),
-                ComponentFlag::UNDERLINE => crate::component::Underline
-                    .on_click(&Context::new(nodes.clone(), document.clone()))
-                    .unwrap_throw(),
-                ComponentFlag::NOTE => crate::component::Note
-                    .on_click(&Context::new(nodes.clone(), document.clone()))
-                    .unwrap_throw(),
-
-                _ => unreachable!(),
-            }
+        let components = data.components.get_all(flags);
+        let listener_id = handle.listener_id;
+
+        drop(data);
+        drop(handle);
+
+        for component in components {
+            component
+                .on_click(nodes.clone(), document.clone())
+                .unwrap_throw();
+
+            emit_event(listener_id, EditorEvent::ComponentClicked(component.flag()));
         }
     }
 
     Ok(())
 }
 
-fn display_toolbar(handler: &Weak<RefCell<Listener>>) -> Result<()> {
+pub(crate) fn display_toolbar(handler: &Weak<RefCell<Listener>>) -> Result<()> {
     let handler = handler.upgrade().expect_throw("Upgrade Listener");
-    let mut handler = handler.borrow_mut();
 
-    if let Some(selection) = handler
-        .listener_id
-        .document()
-        .get_selection()?
-        .filter(|v| !v.is_collapsed())
-    {
-        handler.toolbar.open(selection)?;
-    } else {
-        handler.toolbar.close();
+    let (listener_id, was_open, is_open) = {
+        let mut handler = handler.borrow_mut();
+        let was_open = handler.toolbar.is_open();
+
+        if let Some(selection) = handler
+            .listener_id
+            .document()
+            .get_selection()?
+            .filter(|v| !v.is_collapsed())
+        {
+            handler.toolbar.open(selection)?;
+        } else {
+            handler.toolbar.close();
+        }
+
+        (handler.listener_id, was_open, handler.toolbar.is_open())
+    };
+
+    if !was_open && is_open {
+        emit_event(listener_id, EditorEvent::ToolbarOpened);
+    } else if was_open && !is_open {
+        emit_event(listener_id, EditorEvent::ToolbarClosed);
     }
 
     Ok(())
 }
 
-fn close_toolbar(handler: &SharedListenerType) -> Result<()> {
-    let mut handler = handler.borrow_mut();
+pub(crate) fn close_toolbar(handler: &SharedListenerType) -> Result<()> {
+    let (listener_id, was_open) = {
+        let mut handler = handler.borrow_mut();
+        let was_open = handler.toolbar.is_open();
+
+        handler.toolbar.close();
+
+        (handler.listener_id, was_open)
+    };
 
-    handler.toolbar.close();
+    if was_open {
+        emit_event(listener_id, EditorEvent::ToolbarClosed);
+    }
 
     Ok(())
 }
 
+/// Calls every subscriber of the listener with the event.
+pub(crate) fn emit_event(listener_id: ListenerId, event: EditorEvent) {
+    let Some(listener) = listener_id.try_get() else {
+        return;
+    };
+
+    // Subscribers can use the listener so it can't be borrowed while calling them.
+    let subscribers = listener.borrow().subscribers.all();
+
+    for func in subscribers {
+        func.borrow()(listener_id, &event);
+    }
+}
+
+fn refresh_note_view(listener: &SharedListenerType) {
+    let mut borrow = listener.borrow_mut();
+    let borrow = &mut *borrow;
+    let data = borrow.data.borrow();
+
+    if let Err(e) = borrow.note_view.refresh(&borrow.element, &data) {
+        error!("Unable to refresh the notes: {e:?}");
+    }
+}
+
+/// Emits the annotations which changed since the last call followed by [`EditorEvent::Changed`].
+pub(crate) fn notify_changed(listener_id: ListenerId) {
+    let Some(listener) = listener_id.try_get() else {
+        warn!("Unable to acquire listener. Does it still exist?");
+        return;
+    };
+
+    let events = {
+        let mut borrow = listener.borrow_mut();
+        let after = borrow.data.borrow().tracked_annotations();
+        let before = std::mem::replace(&mut borrow.annotations, after);
+
+        diff_annotations(&before, &borrow.annotations)
+    };
+
+    refresh_note_view(&listener);
+
+    for event in events {
+        emit_event(listener_id, event);
+    }
+
+    emit_event(listener_id, EditorEvent::Changed);
+}
+
 fn reposition_toolbar(handler: &SharedListenerType, selection: Selection) -> Result<()> {
     let mut handler = handler.borrow_mut();
 
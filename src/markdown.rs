@@ -0,0 +1,87 @@
+//! Renders the Markdown of notes to HTML which is safe to insert into the page.
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// URL schemes links and images may use. URLs without a scheme are relative and always allowed.
+const ALLOWED_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Renders the Markdown to HTML.
+///
+/// Raw HTML is escaped and links or images with a disallowed scheme (e.g. `javascript:`) lose
+/// their URL.
+pub(crate) fn render_markdown(value: &str) -> String {
+    let options =
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS;
+
+    let events = Parser::new_ext(value, options).map(|event| match event {
+        Event::Html(html) => Event::Text(html),
+        Event::Start(Tag::Link(kind, url, title)) => {
+            Event::Start(Tag::Link(kind, safe_url(url), title))
+        }
+        Event::Start(Tag::Image(kind, url, title)) => {
+            Event::Start(Tag::Image(kind, safe_url(url), title))
+        }
+        event => event,
+    });
+
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+
+    output
+}
+
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    if is_safe_url(&url) {
+        url
+    } else {
+        CowStr::Borrowed("")
+    }
+}
+
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters inside of the scheme.
+    let cleaned = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect::<String>();
+
+    match cleaned.find([':', '/', '?', '#']) {
+        Some(index) if cleaned[index..].starts_with(':') => {
+            let scheme = cleaned[..index].to_ascii_lowercase();
+
+            ALLOWED_SCHEMES.contains(&scheme.as_str())
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_markdown() {
+        assert_eq!(
+            render_markdown("**bold** and [link](https://example.com)"),
+            "<p><strong>bold</strong> and <a href=\"https://example.com\">link</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn escapes_unsafe_content() {
+        assert_eq!(
+            render_markdown("<script>alert(1)</script>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+
+        assert_eq!(
+            render_markdown("[a](javascript:alert(1)) [b](&#x6A;aVaScRiPt:alert(1))"),
+            "<p><a href=\"\">a</a> <a href=\"\">b</a></p>\n"
+        );
+
+        assert!(!is_safe_url("java\tscript:alert(1)"));
+        assert!(is_safe_url("/relative/path?a=b:c"));
+        assert!(is_safe_url("mailto:someone@example.com"));
+        assert!(!is_safe_url("data:text/html,<script>"));
+    }
+}
@@ -1,4 +1,150 @@
-pub(crate) static CURRENT_VERSION: usize = 0;
+use thiserror::Error;
+#[cfg(feature = "frontend")]
+use wasm_bindgen::JsValue;
 
-// TODO: Handle different versions.
-// Feature gate this so don't need to include the migrations if you don't need it.
+use crate::SaveState;
+
+#[cfg(feature = "migrations")]
+mod v1;
+#[cfg(feature = "migrations")]
+mod v2;
+
+/// The `SaveState` version this library writes.
+pub(crate) static CURRENT_VERSION: usize = 2;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MigrationError {
+    #[error("Save State version {found} is newer than the supported version {current}")]
+    NewerVersion { found: usize, current: usize },
+
+    #[error("No migration registered for Save State version {0}")]
+    MissingStep(usize),
+}
+
+#[cfg(feature = "frontend")]
+impl From<MigrationError> for JsValue {
+    fn from(value: MigrationError) -> Self {
+        JsValue::from_str(&value.to_string())
+    }
+}
+
+/// A single upgrade step. Upgrades a `SaveState` from `from` to `from + 1`.
+pub(crate) struct Migration {
+    pub from: usize,
+    pub apply: fn(&mut SaveState),
+}
+
+/// Every upgrade step, ordered by the version it upgrades from.
+///
+/// Enabled with the `migrations` feature. Without it only the current version can be loaded.
+#[cfg(feature = "migrations")]
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        apply: v1::migrate,
+    },
+    Migration {
+        from: 1,
+        apply: v2::migrate,
+    },
+];
+
+#[cfg(not(feature = "migrations"))]
+static MIGRATIONS: &[Migration] = &[];
+
+/// Upgrades the `SaveState` to the `CURRENT_VERSION`.
+pub(crate) fn migrate(state: &mut SaveState) -> Result<(), MigrationError> {
+    migrate_with(state, MIGRATIONS, CURRENT_VERSION)
+}
+
+fn migrate_with(
+    state: &mut SaveState,
+    migrations: &[Migration],
+    current: usize,
+) -> Result<(), MigrationError> {
+    if state.version > current {
+        return Err(MigrationError::NewerVersion {
+            found: state.version,
+            current,
+        });
+    }
+
+    while state.version < current {
+        let Some(step) = migrations.iter().find(|v| v.from == state.version) else {
+            return Err(MigrationError::MissingStep(state.version));
+        };
+
+        debug!("Migrating Save State from version {}", state.version);
+
+        (step.apply)(state);
+
+        state.version += 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_state(version: usize) -> SaveState {
+        SaveState {
+            version,
+            data: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    fn add_data(state: &mut SaveState) {
+        state.data.push(crate::component::ComponentDataStore::new(
+            crate::ComponentFlag::NOTE,
+            &state.version,
+        ));
+    }
+
+    #[test]
+    fn runs_steps_in_order() {
+        let steps = [
+            Migration {
+                from: 1,
+                apply: add_data,
+            },
+            Migration {
+                from: 0,
+                apply: add_data,
+            },
+        ];
+
+        let mut state = empty_state(0);
+
+        migrate_with(&mut state, &steps, 2).unwrap();
+
+        assert_eq!(state.version, 2);
+        assert_eq!(state.data[0].parse::<usize>(), 0);
+        assert_eq!(state.data[1].parse::<usize>(), 1);
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let mut state = empty_state(CURRENT_VERSION + 1);
+
+        assert_eq!(
+            migrate(&mut state),
+            Err(MigrationError::NewerVersion {
+                found: CURRENT_VERSION + 1,
+                current: CURRENT_VERSION,
+            })
+        );
+    }
+
+    #[test]
+    fn reports_missing_step() {
+        let mut state = empty_state(0);
+
+        assert_eq!(
+            migrate_with(&mut state, &[], 1),
+            Err(MigrationError::MissingStep(0))
+        );
+    }
+}
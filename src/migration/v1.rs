@@ -0,0 +1,6 @@
+use crate::SaveState;
+
+/// Version 1 adds optional text anchors to saved nodes.
+///
+/// Older nodes are placed by index so nothing has to change.
+pub(super) fn migrate(_state: &mut SaveState) {}
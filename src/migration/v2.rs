@@ -0,0 +1,12 @@
+use crate::{component::AnnotationId, SaveState};
+
+/// Version 2 gives every data item a stable [`AnnotationId`].
+///
+/// Ids are given in the order of the data, starting at 1.
+pub(super) fn migrate(state: &mut SaveState) {
+    for (index, store) in state.data.iter_mut().enumerate() {
+        if store.2.is_unset() {
+            store.2 = AnnotationId(index as u64 + 1);
+        }
+    }
+}
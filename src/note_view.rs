@@ -0,0 +1,474 @@
+//! Shows notes without opening the modal. Either as a tooltip of the hovered or focused text or
+//! as cards in a gutter next to the listener Element.
+
+use std::rc::Rc;
+
+use gloo_utils::window;
+use wasm_bindgen::{prelude::Closure, JsCast, UnwrapThrowExt};
+use web_sys::{Document, Element, Event, HtmlElement};
+
+use crate::{
+    component::{Component, Note, NoteData},
+    dom::Dom,
+    helper::{parents_contains_class, TargetCast},
+    listener::{ListenerData, SharedListenerType},
+    util::{ElementEvent, LinePoint, RangeBox},
+    Result, TextContainer, WrappedText,
+};
+
+/// Where the gutter is placed relative to the listener Element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GutterSide {
+    Left,
+    Right,
+}
+
+/// How notes are shown besides the modal which opens when clicking them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteViewConfig {
+    /// Shows the notes' body while its' text is hovered or focused.
+    pub tooltips: bool,
+    /// Lays out note cards next to their text. No gutter if None.
+    pub gutter: Option<GutterSide>,
+    /// Space between the listener Element and the gutter in pixels.
+    pub gutter_offset: f64,
+    /// Minimum space between two cards in pixels.
+    pub card_gap: f64,
+}
+
+impl Default for NoteViewConfig {
+    fn default() -> Self {
+        Self {
+            tooltips: true,
+            gutter: None,
+            gutter_offset: 16.0,
+            card_gap: 8.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct NoteView {
+    pub config: NoteViewConfig,
+
+    tooltip: Option<HtmlElement>,
+    gutter: Option<HtmlElement>,
+}
+
+impl NoteView {
+    /// Re-renders the gutter and makes the notes focusable. Called after every change.
+    pub fn refresh(&mut self, element: &HtmlElement, data: &ListenerData) -> Result<()> {
+        self.hide_tooltip();
+
+        set_notes_focusable(&data.nodes, self.config.tooltips)?;
+
+        self.render_gutter(element, data)
+    }
+
+    /// Shows the notes of the hovered or focused note container.
+    pub fn show_tooltip(&mut self, container: &Element, data: &ListenerData) -> Result<()> {
+        self.hide_tooltip();
+
+        let Some(wrapped) = find_note_text(&data.nodes, container) else {
+            return Ok(());
+        };
+
+        let notes = note_bodies(data, wrapped.flag.data_of(Note::FLAG));
+
+        if notes.is_empty() {
+            return Ok(());
+        }
+
+        let document = data.listener_id.document();
+
+        let tooltip: HtmlElement = document.create_element("div")?.unchecked_into();
+        tooltip.set_class_name("editor-note-tooltip");
+        tooltip.set_attribute("role", "tooltip")?;
+
+        for (_, note) in notes {
+            let item = document.create_element("div")?;
+            item.set_class_name("editor-note-tooltip-item");
+            item.set_inner_html(&note.render_html());
+            tooltip.append_child(&item)?;
+        }
+
+        let rect = container.get_bounding_client_rect();
+        let (scroll_x, scroll_y) = scroll_offset()?;
+
+        let style = tooltip.style();
+        style.set_property("left", &format!("{}px", rect.left() + scroll_x))?;
+        style.set_property("top", &format!("{}px", rect.bottom() + scroll_y + 4.0))?;
+
+        document.body().unwrap_throw().append_child(&tooltip)?;
+
+        self.tooltip = Some(tooltip);
+
+        Ok(())
+    }
+
+    pub fn hide_tooltip(&mut self) {
+        if let Some(tooltip) = self.tooltip.take() {
+            tooltip.remove();
+        }
+    }
+
+    fn render_gutter(&mut self, element: &HtmlElement, data: &ListenerData) -> Result<()> {
+        if let Some(gutter) = self.gutter.take() {
+            gutter.remove();
+        }
+
+        let Some(side) = self.config.gutter else {
+            return Ok(());
+        };
+
+        let document = data.listener_id.document();
+        let anchors = note_anchors(&document, data)?;
+
+        let rect = element.get_bounding_client_rect();
+        let (scroll_x, scroll_y) = scroll_offset()?;
+
+        let gutter: HtmlElement = document.create_element("div")?.unchecked_into();
+        gutter.set_class_name("editor-note-gutter");
+
+        let style = gutter.style();
+        style.set_property("top", &format!("{}px", rect.top() + scroll_y))?;
+
+        match side {
+            GutterSide::Left => {
+                let left = rect.left() + scroll_x - self.config.gutter_offset;
+
+                style.set_property("left", &format!("{left}px"))?;
+                style.set_property("transform", "translateX(-100%)")?;
+            }
+            GutterSide::Right => {
+                let left = rect.right() + scroll_x + self.config.gutter_offset;
+
+                style.set_property("left", &format!("{left}px"))?;
+            }
+        }
+
+        document.body().unwrap_throw().append_child(&gutter)?;
+
+        let mut cards = Vec::new();
+
+        for (point, note) in anchors {
+            let card: HtmlElement = document.create_element("div")?.unchecked_into();
+            card.set_class_name("editor-note-card");
+            card.set_inner_html(&note.render_html());
+            gutter.append_child(&card)?;
+
+            // Relative to the gutter which starts at the top of the listener Element.
+            cards.push((card, point.y - rect.top()));
+        }
+
+        let wanted = cards
+            .iter()
+            .map(|(card, top)| (*top, card.offset_height() as f64))
+            .collect::<Vec<_>>();
+
+        for ((card, _), top) in cards
+            .iter()
+            .zip(layout_cards(&wanted, self.config.card_gap))
+        {
+            card.style().set_property("top", &format!("{top}px"))?;
+        }
+
+        self.gutter = Some(gutter);
+
+        Ok(())
+    }
+}
+
+impl Drop for NoteView {
+    fn drop(&mut self) {
+        self.hide_tooltip();
+
+        if let Some(gutter) = self.gutter.take() {
+            gutter.remove();
+        }
+    }
+}
+
+/// Shows the tooltips while hovering or focusing notes. Re-renders the gutter on resize.
+pub(crate) fn register_note_view_events(
+    listener_rc: &SharedListenerType,
+    listener_class: String,
+) -> Result<()> {
+    let document = listener_rc.borrow().listener_id.document();
+
+    for (name, is_leaving) in [("mouseover", false), ("focusin", false), ("focusout", true)] {
+        let listener = Rc::downgrade(listener_rc);
+        let listener_class = listener_class.clone();
+
+        let function: Closure<dyn FnMut(Event)> = Closure::new(move |event: Event| {
+            let Some(listener) = listener.upgrade() else {
+                return;
+            };
+
+            // Focus events can be dispatched while the listener is in use, e.g. by the toolbar.
+            let Ok(mut borrow) = listener.try_borrow_mut() else {
+                return;
+            };
+            let borrow = &mut *borrow;
+
+            if !borrow.note_view.config.tooltips {
+                return;
+            }
+
+            let target: Element = event.target_unchecked_into();
+
+            let container = target
+                .closest(&format!(".{}", Note::CLASS_NAME.unwrap_throw()))
+                .ok()
+                .flatten()
+                .filter(|v| !is_leaving && parents_contains_class(v.clone(), &listener_class));
+
+            if let Some(container) = container {
+                let Ok(data) = borrow.data.try_borrow() else {
+                    return;
+                };
+
+                borrow
+                    .note_view
+                    .show_tooltip(&container, &data)
+                    .unwrap_throw();
+            } else {
+                borrow.note_view.hide_tooltip();
+            }
+        });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.clone().unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback(name, f),
+            Box::new(move |t, f| t.remove_event_listener_with_callback(name, f)),
+        ));
+    }
+
+    // Create the resize listener
+    {
+        let listener = Rc::downgrade(listener_rc);
+
+        let function: Closure<dyn FnMut(Event)> = Closure::new(move |_: Event| {
+            let Some(listener) = listener.upgrade() else {
+                return;
+            };
+
+            let mut borrow = listener.borrow_mut();
+            let borrow = &mut *borrow;
+
+            if borrow.note_view.config.gutter.is_some() {
+                borrow
+                    .note_view
+                    .refresh(&borrow.element, &borrow.data.borrow())
+                    .unwrap_throw();
+            }
+        });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            window().unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback("resize", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("resize", f)),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Makes the note spans focusable so focusing them shows the tooltip.
+///
+/// Only the tabindex of our own spans is changed. Spans which are no longer notes lose it again.
+pub(crate) fn set_notes_focusable<D: Dom>(
+    containers: &[TextContainer<D>],
+    focusable: bool,
+) -> std::result::Result<(), D::Error> {
+    for text in containers.iter().flat_map(|v| &v.text) {
+        let Some(span) = text.wrapper() else {
+            continue;
+        };
+
+        if focusable && text.intersects_flag(Note::FLAG) {
+            D::set_attribute(span, "tabindex", "0")?;
+        } else if D::has_attribute(span, "tabindex") {
+            D::remove_attribute(span, "tabindex")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The note text wrapped by the span.
+pub(crate) fn find_note_text<'a, D: Dom>(
+    containers: &'a [TextContainer<D>],
+    span: &D::Element,
+) -> Option<&'a WrappedText<D>> {
+    containers
+        .iter()
+        .flat_map(|v| &v.text)
+        .find(|v| v.wrapper() == Some(span) && v.intersects_flag(Note::FLAG))
+}
+
+/// Returns the tops of the cards after moving them down so they don't overlap.
+///
+/// Each card is its' wanted top along with its' height. Cards stay in the order of their wanted
+/// top and keep at least `gap` between each other.
+pub(crate) fn layout_cards(cards: &[(f64, f64)], gap: f64) -> Vec<f64> {
+    let mut order = (0..cards.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| cards[a].0.total_cmp(&cards[b].0));
+
+    let mut tops = vec![0.0; cards.len()];
+    let mut next_free = f64::NEG_INFINITY;
+
+    for index in order {
+        let (wanted, height) = cards[index];
+        let top = wanted.max(next_free);
+
+        tops[index] = top;
+        next_free = top + height + gap;
+    }
+
+    tops
+}
+
+/// The data of the notes. Skips ids without valid data.
+fn note_bodies(data: &ListenerData, ids: impl Iterator<Item = u32>) -> Vec<(u32, NoteData)> {
+    ids.filter_map(|id| {
+        let note = data.get_data_by_id(Note::FLAG, id)?.try_parse().ok()?;
+
+        Some((id, note))
+    })
+    .collect()
+}
+
+/// Where every note starts along with its' data. In the order they appear.
+fn note_anchors(document: &Document, data: &ListenerData) -> Result<Vec<(LinePoint, NoteData)>> {
+    let mut seen = Vec::new();
+    let mut anchors = Vec::new();
+
+    for node in &data.nodes {
+        for text in &node.text {
+            let ids = text
+                .flag
+                .data_of(Note::FLAG)
+                .filter(|id| !seen.contains(id))
+                .collect::<Vec<_>>();
+
+            if ids.is_empty() {
+                continue;
+            }
+
+            let range = document.create_range()?;
+            range.select_node_contents(&text.node)?;
+
+            let point = RangeBox::from_range(&range).start;
+
+            for (id, note) in note_bodies(data, ids.into_iter()) {
+                seen.push(id);
+                anchors.push((point, note));
+            }
+        }
+    }
+
+    Ok(anchors)
+}
+
+fn scroll_offset() -> Result<(f64, f64)> {
+    let window = window();
+
+    Ok((window.scroll_x()?, window.scroll_y()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        component::{ComponentRegistry, FlagsWithData, SingleFlagWithData},
+        dom::{MemoryDom, MemoryNode},
+        store::apply_saved_nodes,
+        SavedNode, SavedNodeFlag,
+    };
+
+    use super::*;
+
+    #[test]
+    fn focus_note_span() {
+        let root = MemoryNode::element("p");
+        root.set_attribute("tabindex", "-1");
+
+        let text = MemoryNode::text("Some noted text");
+        root.append_child(&text);
+
+        let mut container =
+            TextContainer::<MemoryDom>::new(text.clone(), Rc::new(ComponentRegistry::default()))
+                .unwrap();
+        let noted = container.split_node(&text, 5).unwrap();
+        container
+            .add_flag_to_node(&noted, FlagsWithData::new_with_data(Note::FLAG, 3))
+            .unwrap();
+
+        let containers = [container];
+        set_notes_focusable(&containers, true).unwrap();
+
+        // Focus lands on the note span which the tooltip is shown for.
+        let span = root.children()[1].clone();
+
+        assert_eq!(span.attribute("class").as_deref(), Some("editor-note"));
+        assert_eq!(span.attribute("tabindex").as_deref(), Some("0"));
+
+        let focused = find_note_text(&containers, &span).unwrap();
+        assert_eq!(focused.flag.data_of(Note::FLAG).collect::<Vec<_>>(), [3]);
+
+        // The paragraph isn't a note.
+        assert!(find_note_text(&containers, &root).is_none());
+
+        // Only our tabindex is removed.
+        set_notes_focusable(&containers, false).unwrap();
+
+        assert_eq!(span.attribute("tabindex"), None);
+        assert_eq!(root.attribute("tabindex").as_deref(), Some("-1"));
+    }
+
+    #[test]
+    fn loaded_notes_focusable() {
+        let root = MemoryNode::element("p");
+
+        let text = MemoryNode::text("Some noted text");
+        root.append_child(&text);
+
+        let mut containers =
+            [
+                TextContainer::<MemoryDom>::new(text, Rc::new(ComponentRegistry::default()))
+                    .unwrap(),
+            ];
+
+        let saved = SavedNode::new(
+            0,
+            vec![SavedNodeFlag {
+                offset: 5,
+                length: None,
+                flags: vec![SingleFlagWithData::new(Note::FLAG, 0)],
+            }],
+        );
+
+        let unplaced = apply_saved_nodes(vec![saved], &mut containers, |_| Ok(None)).unwrap();
+        assert!(unplaced.is_empty());
+
+        // Same as registering a listener with the loaded state.
+        set_notes_focusable(&containers, NoteViewConfig::default().tooltips).unwrap();
+
+        let span = root.children()[1].clone();
+
+        assert_eq!(span.attribute("class").as_deref(), Some("editor-note"));
+        assert_eq!(span.attribute("tabindex").as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn cards_dont_overlap() {
+        // Wanted top and height. The second card would overlap the first.
+        let cards = [(100.0, 50.0), (0.0, 40.0), (120.0, 30.0), (400.0, 10.0)];
+
+        assert_eq!(layout_cards(&cards, 8.0), [100.0, 0.0, 158.0, 400.0]);
+
+        assert!(layout_cards(&[], 8.0).is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+//! Renders the annotations of a [`SaveState`] into HTML without a browser.
+//!
+//! The HTML is parsed like the browser does and split with the same [`TextContainer`]s the
+//! listener uses, so the spans are identical to the ones created in the browser.
+
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use crate::{
+    component::ComponentRegistry,
+    dom::{MemoryDom, MemoryDomError, MemoryNode},
+    store::apply_saved_nodes,
+    ContainerHint, MigrationError, SaveState, TextContainer,
+};
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
+
+    #[error(transparent)]
+    Dom(#[from] MemoryDomError),
+}
+
+/// Applies the annotations of the Save State to the HTML. Uses the built-in Components if
+/// `components` is None.
+///
+/// Returns the `innerHTML` a container with the HTML has after loading the state in the browser.
+/// Saved nodes which can't be placed are skipped.
+pub fn render_html(
+    html: &str,
+    mut state: SaveState,
+    components: Option<ComponentRegistry>,
+) -> Result<String, RenderError> {
+    state.migrate()?;
+
+    let container = MemoryNode::parse_fragment(html);
+    let nodes = container.text_nodes();
+
+    let components = Rc::new(components.unwrap_or_default());
+
+    let mut containers = nodes
+        .iter()
+        .map(|text| TextContainer::<MemoryDom>::new(text.clone(), components.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let unplaced = apply_saved_nodes(state.nodes, &mut containers, |hint| {
+        Ok(resolve_hint(&container, hint).and_then(|element| {
+            let first = nodes.iter().position(|v| element.contains(v))?;
+            let last = nodes.iter().rposition(|v| element.contains(v))?;
+
+            Some(first..=last)
+        }))
+    })?;
+
+    if !unplaced.is_empty() {
+        warn!("Unable to place {} saved nodes", unplaced.len());
+    }
+
+    // Has to happen before the containers are dropped since that unwraps the text again.
+    Ok(container.inner_html())
+}
+
+/// Returns the Element the hint points to.
+///
+/// Only CSS hints in the format `css_path` creates are supported. Anything else is None which
+/// means the whole text is searched.
+fn resolve_hint(container: &MemoryNode, hint: &ContainerHint) -> Option<MemoryNode> {
+    let ContainerHint::Css(selector) = hint else {
+        debug!("XPath hints aren't supported when rendering");
+        return None;
+    };
+
+    let mut parts = selector.split(" > ");
+
+    if parts.next()? != ":scope" {
+        return None;
+    }
+
+    let mut current = container.clone();
+
+    for part in parts {
+        let (tag, index) = part.strip_suffix(')')?.split_once(":nth-child(")?;
+        let index = index.parse::<usize>().ok()?.checked_sub(1)?;
+
+        current = current
+            .children()
+            .into_iter()
+            .filter(|v| v.tag_name().is_some())
+            .nth(index)?;
+
+        if !current.tag_name()?.eq_ignore_ascii_case(tag) {
+            return None;
+        }
+    }
+
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        component::{ComponentDataStore, NoteData, SingleFlagWithData},
+        migration::CURRENT_VERSION,
+        ComponentFlag, SavedNode, SavedNodeFlag, TextAnchor,
+    };
+
+    use super::*;
+
+    fn flagged(offset: u32, length: Option<u32>, flag: ComponentFlag) -> Vec<SavedNodeFlag> {
+        vec![SavedNodeFlag {
+            offset,
+            length,
+            flags: vec![SingleFlagWithData::new(flag, 0)],
+        }]
+    }
+
+    #[test]
+    fn render_annotations() {
+        let html = "<p>Hello <b>big</b> World</p>\n<p>Second &amp; last</p>";
+
+        let state = SaveState {
+            version: CURRENT_VERSION,
+            data: vec![ComponentDataStore::new(
+                ComponentFlag::NOTE,
+                &NoteData::new(String::from("Note")),
+            )],
+            nodes: vec![
+                SavedNode::new(0, flagged(0, Some(5), ComponentFlag::HIGHLIGHT)),
+                SavedNode::new(4, flagged(7, None, ComponentFlag::NOTE)),
+                // Past the last Text Node.
+                SavedNode::new(9, flagged(0, None, ComponentFlag::BOLD)),
+            ],
+        };
+
+        assert_eq!(
+            render_html(html, state, None).unwrap(),
+            "<p><span class=\"editor-highlight yellow\">Hello</span> <b>big</b> World</p>\n\
+             <p>Second <span class=\"editor-note\">&amp; last</span></p>"
+        );
+    }
+
+    #[test]
+    fn render_anchored() {
+        let html = "<div><p>Same</p></div><p>Same</p>";
+        let text = "SameSame".encode_utf16().collect::<Vec<_>>();
+
+        let container = MemoryNode::parse_fragment(html);
+        let hint = ContainerHint::Css(String::from(":scope > p:nth-child(2)"));
+
+        assert_eq!(
+            resolve_hint(&container, &hint),
+            Some(container.children()[1].clone())
+        );
+        assert_eq!(
+            resolve_hint(&container, &ContainerHint::XPath(String::from("./p"))),
+            None
+        );
+
+        // Saved for the first node while the anchor points to the second one.
+        let mut saved = SavedNode::new(0, flagged(0, None, ComponentFlag::UNDERLINE));
+        saved.anchor = Some(TextAnchor::new(&text, 4..8, Some(hint)));
+
+        let state = SaveState {
+            version: CURRENT_VERSION,
+            data: Vec::new(),
+            nodes: vec![saved],
+        };
+
+        assert_eq!(
+            render_html(html, state, None).unwrap(),
+            "<div><p>Same</p></div><p><span class=\"editor-underline\">Same</span></p>"
+        );
+    }
+}
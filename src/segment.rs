@@ -0,0 +1,314 @@
+//! Finding words inside the text of a listener.
+//!
+//! Segmentation works on `&str` byte offsets while the DOM uses UTF-16 code units.
+
+use std::ops::Range;
+
+use gloo_utils::window;
+use unicode_segmentation::UnicodeSegmentation;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Node, Text};
+
+use crate::{text::return_all_text_nodes, Result};
+
+/// Decides where words start and end.
+///
+/// The default is [`UnicodeWords`]. Implement this for languages UAX #29 can't segment, e.g. a
+/// dictionary based segmenter for Chinese or Japanese.
+pub trait WordBoundary {
+    /// Returns the byte range of the word at the byte `offset`.
+    fn word_at(&self, text: &str, offset: usize) -> Option<Range<usize>>;
+}
+
+/// Word boundaries from the Unicode Text Segmentation rules (UAX #29).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnicodeWords;
+
+impl WordBoundary for UnicodeWords {
+    fn word_at(&self, text: &str, offset: usize) -> Option<Range<usize>> {
+        let segments = text
+            .split_word_bound_indices()
+            .map(|(start, value)| start..start + value.len())
+            .collect::<Vec<_>>();
+
+        let is_word = |range: &Range<usize>| text[range.clone()].chars().any(char::is_alphanumeric);
+
+        let index = segments
+            .iter()
+            .position(|v| v.contains(&offset))
+            .unwrap_or(segments.len());
+
+        if let Some(found) = segments.get(index).filter(|v| is_word(v)) {
+            return Some(found.clone());
+        }
+
+        // We're right after a word. e.g. the right half of its' last character was clicked.
+        if let Some(found) = index
+            .checked_sub(1)
+            .and_then(|i| segments.get(i))
+            .filter(|v| v.end == offset && is_word(v))
+        {
+            return Some(found.clone());
+        }
+
+        segments[index..].iter().find(|v| is_word(v)).cloned()
+    }
+}
+
+/// How much text is selected around a point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Granularity {
+    #[default]
+    Word,
+    Sentence,
+    /// Everything inside the closest block Element.
+    Paragraph,
+}
+
+impl Granularity {
+    /// Double-click selects a word, triple-click a sentence and any more a paragraph.
+    pub fn from_click_count(count: i32) -> Option<Self> {
+        match count {
+            ..=1 => None,
+            2 => Some(Self::Word),
+            3 => Some(Self::Sentence),
+            _ => Some(Self::Paragraph),
+        }
+    }
+
+    /// Returns the byte range around the byte `offset`.
+    pub fn range_at(
+        self,
+        text: &str,
+        offset: usize,
+        word_boundary: &dyn WordBoundary,
+    ) -> Option<Range<usize>> {
+        let found = match self {
+            Self::Word => word_boundary.word_at(text, offset)?,
+
+            Self::Sentence => text
+                .split_sentence_bound_indices()
+                .map(|(start, value)| start..start + value.len())
+                .find(|v| v.contains(&offset) || v.end == text.len())?,
+
+            Self::Paragraph => 0..text.len(),
+        };
+
+        Some(trim_range(text, found)).filter(|v| !v.is_empty())
+    }
+}
+
+/// Removes the whitespace surrounding the range.
+fn trim_range(text: &str, range: Range<usize>) -> Range<usize> {
+    let value = &text[range.clone()];
+    let start = range.start + (value.len() - value.trim_start().len());
+
+    start..start + value.trim().len()
+}
+
+/// The Text Nodes of a block Element joined together.
+pub(crate) struct TextRun {
+    nodes: Vec<Text>,
+    /// UTF-16 length of each node.
+    lengths: Vec<usize>,
+    pub text: String,
+}
+
+impl TextRun {
+    /// Every Text Node inside the same block Element as the `node`. Stops at the `container`.
+    pub fn around(node: &Node, container: &Element) -> Self {
+        let block = block_ancestor(node, container);
+
+        Self::new(
+            return_all_text_nodes(&block)
+                .into_iter()
+                .filter(|v| block_ancestor(v, container) == block)
+                .collect(),
+        )
+    }
+
+    pub fn new(nodes: Vec<Text>) -> Self {
+        let mut text = String::new();
+        let mut lengths = Vec::new();
+
+        for node in &nodes {
+            let value = node.data();
+
+            lengths.push(value.encode_utf16().count());
+            text += &value;
+        }
+
+        Self {
+            nodes,
+            lengths,
+            text,
+        }
+    }
+
+    /// Byte offset into the joined text from an offset inside one of the nodes.
+    pub fn offset_of(&self, node: &Node, offset: u32) -> Option<usize> {
+        let index = self
+            .nodes
+            .iter()
+            .position(|v| v.unchecked_ref::<Node>() == node)?;
+
+        let utf16 = self.lengths[..index].iter().sum::<usize>() + offset as usize;
+
+        Some(utf16_to_byte(&self.text, utf16))
+    }
+
+    /// Creates a DOM Range from a byte range of the joined text.
+    pub fn create_range(&self, range: Range<usize>) -> Result<web_sys::Range> {
+        let (start_index, start_offset) =
+            locate(&self.lengths, byte_to_utf16(&self.text, range.start), false);
+        let (end_index, end_offset) =
+            locate(&self.lengths, byte_to_utf16(&self.text, range.end), true);
+
+        let dom_range = web_sys::Range::new()?;
+
+        dom_range.set_start(&self.nodes[start_index], start_offset as u32)?;
+        dom_range.set_end(&self.nodes[end_index], end_offset as u32)?;
+
+        Ok(dom_range)
+    }
+}
+
+/// The closest Element which isn't displayed inline. Returns the `container` if there's none.
+pub(crate) fn block_ancestor(node: &Node, container: &Element) -> Element {
+    let mut current = node.parent_element();
+
+    while let Some(element) = current {
+        if &element == container || !is_inline(&element) {
+            return element;
+        }
+
+        current = element.parent_element();
+    }
+
+    container.clone()
+}
+
+fn is_inline(element: &Element) -> bool {
+    window()
+        .get_computed_style(element)
+        .ok()
+        .flatten()
+        .and_then(|style| style.get_property_value("display").ok())
+        .is_some_and(|display| display.starts_with("inline") || display == "contents")
+}
+
+pub(crate) fn utf16_to_byte(text: &str, utf16: usize) -> usize {
+    let mut count = 0;
+
+    for (index, value) in text.char_indices() {
+        if count >= utf16 {
+            return index;
+        }
+
+        count += value.len_utf16();
+    }
+
+    text.len()
+}
+
+pub(crate) fn byte_to_utf16(text: &str, byte: usize) -> usize {
+    text[..byte].encode_utf16().count()
+}
+
+/// Finds the node index and offset inside of it from an offset into all of them.
+///
+/// Offsets between two nodes are placed at the end of the first one if `prefer_end` is true.
+pub(crate) fn locate(lengths: &[usize], mut offset: usize, prefer_end: bool) -> (usize, usize) {
+    for (index, &length) in lengths.iter().enumerate() {
+        if offset < length || (prefer_end && offset == length) {
+            return (index, offset);
+        }
+
+        offset -= length;
+    }
+
+    let last = lengths.len().saturating_sub(1);
+
+    (last, lengths.get(last).copied().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, offset: usize) -> Option<&str> {
+        UnicodeWords.word_at(text, offset).map(|v| &text[v])
+    }
+
+    #[test]
+    fn unicode_words() {
+        let text = "“Don’t panic,” said the café owner.";
+
+        assert_eq!(word(text, 0), Some("Don’t"));
+        assert_eq!(word(text, 5), Some("Don’t"));
+        assert_eq!(word(text, text.find("café").unwrap() + 4), Some("café"));
+
+        // Right after a word.
+        let end = text.find("panic").unwrap() + 5;
+        assert_eq!(word(text, end), Some("panic"));
+
+        // Between words select the next one.
+        let space = text.find(" owner").unwrap();
+        assert_eq!(word(text, space + 1), Some("owner"));
+
+        assert_eq!(word("the owner", 9), Some("owner"));
+        assert_eq!(word("...", 1), None);
+    }
+
+    #[test]
+    fn granularity() {
+        let text = "  It was late. “Are you there?” She left.\n";
+
+        let range = |granularity: Granularity, offset: usize| {
+            granularity
+                .range_at(text, offset, &UnicodeWords)
+                .map(|v| &text[v])
+        };
+
+        let offset = text.find("there").unwrap();
+
+        assert_eq!(range(Granularity::Word, offset), Some("there"));
+        assert_eq!(
+            range(Granularity::Sentence, offset),
+            Some("“Are you there?”")
+        );
+        assert_eq!(range(Granularity::Sentence, 3), Some("It was late."));
+        assert_eq!(range(Granularity::Sentence, text.len()), Some("She left."));
+        assert_eq!(
+            range(Granularity::Paragraph, 3),
+            Some("It was late. “Are you there?” She left.")
+        );
+
+        assert_eq!(Granularity::from_click_count(1), None);
+        assert_eq!(
+            Granularity::from_click_count(3),
+            Some(Granularity::Sentence)
+        );
+    }
+
+    #[test]
+    fn utf16_offsets() {
+        let text = "🦀 crab é";
+
+        assert_eq!(utf16_to_byte(text, 3), 5);
+        assert_eq!(byte_to_utf16(text, 5), 3);
+        assert_eq!(utf16_to_byte(text, 100), text.len());
+        assert_eq!(byte_to_utf16(text, text.len()), 9);
+    }
+
+    #[test]
+    fn locate_offsets() {
+        let lengths = [3, 4];
+
+        assert_eq!(locate(&lengths, 0, false), (0, 0));
+        assert_eq!(locate(&lengths, 3, false), (1, 0));
+        assert_eq!(locate(&lengths, 3, true), (0, 3));
+        assert_eq!(locate(&lengths, 7, false), (1, 4));
+        assert_eq!(locate(&lengths, 7, true), (1, 4));
+    }
+}
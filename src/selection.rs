@@ -5,8 +5,9 @@ use wasm_bindgen::UnwrapThrowExt;
 use web_sys::{Range, Selection, Text};
 
 use crate::{
-    component::FlagsWithData, text::get_all_text_nodes_in_container, Component, ComponentFlag,
-    Result, SharedListenerData,
+    component::FlagsWithData, dom::Dom, store::SavedNodeFlag,
+    text::get_all_text_nodes_in_container, Component, ComponentFlag, Result, SharedListenerData,
+    TextContainer,
 };
 
 pub struct NodeContainer {
@@ -117,6 +118,8 @@ impl NodeContainer {
         let flag =
             FlagsWithData::new_with_data(D::FLAG, data.unwrap_or_else(D::get_default_data_id));
 
+        let snapshot = self.snapshot_flags();
+
         if self.does_selected_intersect(D::ALLOWED_SIBLINGS.complement()) {
             // If Allowed Siblings is empty and we don't overwrite the non-allowed ones.
             if D::ALLOWED_SIBLINGS.is_empty() && !D::OVERWRITE_INVALID {
@@ -152,6 +155,8 @@ impl NodeContainer {
             page_data.update_container(text, flag.clone())?;
         }
 
+        page_data.record_flag_changes(snapshot);
+
         self.reload_selection()?;
 
         Ok(Ok(()))
@@ -166,6 +171,8 @@ impl NodeContainer {
         // TODO: Should I split when removing selection?
         // self.split_and_acq_text_nodes()?;
 
+        let snapshot = self.snapshot_flags();
+
         let page_data = self.data.upgrade().expect_throw("data upgrade");
         let mut page_data = page_data.borrow_mut();
 
@@ -173,6 +180,8 @@ impl NodeContainer {
             page_data.remove_component_node_flag(text, &flag)?;
         }
 
+        page_data.record_flag_changes(snapshot);
+
         self.reload_selection()?;
 
         Ok(true)
@@ -187,6 +196,8 @@ impl NodeContainer {
             return Ok(false);
         }
 
+        let snapshot = self.snapshot_flags();
+
         self.split_and_acq_text_nodes()?;
 
         if self.does_selected_contain(&flag) {
@@ -209,11 +220,26 @@ impl NodeContainer {
             }
         }
 
+        self.data
+            .upgrade()
+            .expect_throw("data upgrade")
+            .borrow_mut()
+            .record_flag_changes(snapshot);
+
         self.reload_selection()?;
 
         Ok(true)
     }
 
+    /// Snapshot of the selected nodes' flags. Used for undo history.
+    fn snapshot_flags(&self) -> Vec<(usize, Vec<SavedNodeFlag>)> {
+        self.data
+            .upgrade()
+            .expect_throw("data upgrade")
+            .borrow()
+            .snapshot_flags(&self.nodes)
+    }
+
     pub fn reload_selection(&self) -> Result<()> {
         let selection = window().get_selection()?.unwrap();
 
@@ -255,64 +281,60 @@ impl NodeContainer {
 
         self.was_text_split = true;
 
-        // We've selected inside a single node.
-        if self.nodes.is_empty() {
-            return Ok(());
-        } else if self.nodes.len() == 1 {
-            let split = self.nodes.remove(0);
-            self.separate_text_node(split, Some(self.start_offset), Some(self.end_offset))?;
-        } else {
-            let nodes = mem::take(&mut self.nodes);
-            let node_count = nodes.len();
-
-            for (i, split) in nodes.into_iter().enumerate() {
-                self.separate_text_node(
-                    split,
-                    (i == 0).then_some(self.start_offset),
-                    (i + 1 == node_count).then_some(self.end_offset),
-                )?;
-            }
-        }
+        let page_data = self.data.upgrade().expect_throw("data upgrade");
+        let mut page_data = page_data.borrow_mut();
+
+        self.nodes = split_selected_nodes(
+            &mut page_data.nodes,
+            mem::take(&mut self.nodes),
+            self.start_offset,
+            self.end_offset,
+        )?;
 
         Ok(())
     }
+}
 
-    fn separate_text_node(
-        &mut self,
-        text: Text,
-        start_offset: Option<u32>,
-        end_offset: Option<u32>,
-    ) -> Result<()> {
-        // TODO: Determine if we should remove white-space from the end of a text node.
-
-        let page_data = self.data.upgrade().expect_throw("data upgrade");
-        let mut page_data = page_data.borrow_mut();
+/// Splits the first and last selected Text Nodes at the selection offsets.
+///
+/// Returns the Text Nodes which are fully inside of the selection.
+pub(crate) fn split_selected_nodes<D: Dom>(
+    containers: &mut [TextContainer<D>],
+    nodes: Vec<D::Text>,
+    start_offset: u32,
+    end_offset: u32,
+) -> std::result::Result<Vec<D::Text>, D::Error> {
+    let node_count = nodes.len();
+    let mut selected = Vec::with_capacity(node_count);
 
-        // If component node already exists.
-        let mut comp_node = page_data.get_text_container_mut(&text).unwrap_throw();
+    for (i, text) in nodes.into_iter().enumerate() {
+        // TODO: Determine if we should remove white-space from the end of a text node.
 
-        debug!("Node cached");
+        let mut comp_node = containers
+            .iter_mut()
+            .find_map(|v| v.find_node_return_mut_ref(&text))
+            .expect("unable to find Text Container");
 
-        if let Some(end_offset) = end_offset {
-            if end_offset != text.length() {
-                debug!(" - splitting end: {} != {}", end_offset, text.length());
+        if i + 1 == node_count && end_offset != D::text_length(&text) {
+            debug!(
+                " - splitting end: {} != {}",
+                end_offset,
+                D::text_length(&text)
+            );
 
-                comp_node.split_node(&text, end_offset)?;
-            }
+            comp_node.split_node(&text, end_offset)?;
         }
 
-        if let Some(start_offset) = start_offset.filter(|v| *v != 0) {
+        if i == 0 && start_offset != 0 {
             debug!(" - splitting start: {}", start_offset);
 
-            let right_text = comp_node.split_node(&text, start_offset)?;
-
-            self.nodes.push(right_text);
+            selected.push(comp_node.split_node(&text, start_offset)?);
         } else {
-            self.nodes.push(text);
+            selected.push(text);
         }
-
-        Ok(())
     }
+
+    Ok(selected)
 }
 
 pub fn get_nodes_in_selection(
@@ -348,3 +370,52 @@ pub fn get_nodes_in_selection(
 pub fn create_container(nodes: Vec<Text>, data: SharedListenerData) -> Result<NodeContainer> {
     Ok(NodeContainer::new(data, nodes, 0, 0))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        component::ComponentRegistry,
+        dom::{MemoryDom, MemoryNode},
+    };
+
+    use super::*;
+
+    #[test]
+    fn split_selection() {
+        let components = Rc::new(ComponentRegistry::default());
+        let root = MemoryNode::element("div");
+
+        let mut containers = ["First", "Second", "Third"]
+            .into_iter()
+            .map(|value| {
+                let text = MemoryNode::text(value);
+                root.append_child(&text);
+
+                TextContainer::<MemoryDom>::new(text, components.clone()).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // "st" -> "Second" -> "Th"
+        let selected = split_selected_nodes(&mut containers, root.text_nodes(), 3, 2).unwrap();
+
+        assert_eq!(
+            selected.iter().map(MemoryNode::data).collect::<Vec<_>>(),
+            ["st", "Second", "Th"]
+        );
+        assert_eq!(containers[0].text.len(), 2);
+        assert_eq!(containers[1].text.len(), 1);
+        assert_eq!(containers[2].text.len(), 2);
+        assert_eq!(containers[2].text[1].offset, 2);
+
+        // Inside of a single Text Node.
+        let second = containers[1].text[0].node.clone();
+        let selected = split_selected_nodes(&mut containers, vec![second], 1, 4).unwrap();
+
+        assert_eq!(selected[0].data(), "eco");
+        assert_eq!(containers[1].text_content(), "Second");
+        assert_eq!(containers[1].text.len(), 3);
+        assert_eq!(root.text_content(), "FirstSecondThird");
+    }
+}
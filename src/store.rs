@@ -1,33 +1,61 @@
+#[cfg(any(feature = "frontend", feature = "render"))]
+use std::ops::{Range, RangeInclusive};
+#[cfg(feature = "frontend")]
+use std::rc::Rc;
+
 use bytes::Buf;
 use serde::{Deserialize, Serialize};
-use web_sys::{Document, HtmlElement, Text};
+use thiserror::Error;
+#[cfg(feature = "frontend")]
+use web_sys::{Document, Element, HtmlElement, Text};
+
+use crate::{
+    anchor::{ContainerHint, TextAnchor, TextPositionSelector, TextQuoteSelector},
+    component::{AnnotationId, ComponentDataStore, FlagsWithData, SingleFlagWithData},
+    dom::Dom,
+    encoding::{Encoding, Reader, Writer},
+    migration::{self, MigrationError, CURRENT_VERSION},
+    WrappedText,
+};
 
+#[cfg(any(feature = "frontend", feature = "render"))]
+use crate::TextContainer;
+
+#[cfg(feature = "frontend")]
 use crate::{
-    component::{ComponentDataStore, FlagsWithData, SingleFlagWithData},
-    listener::{register_with_data, ListenerData, ListenerEvent, ListenerHandle, MouseListener},
-    migration::CURRENT_VERSION,
+    anchor,
+    component::ComponentRegistry,
+    event::ListenerEvent,
+    listener::{register_with_data, ListenerData, ListenerHandle, MouseListener},
     text::return_all_text_nodes,
-    ComponentFlag, ListenerId, Result, WrappedText,
+    ListenerId, Result,
 };
 
+#[cfg(feature = "frontend")]
+/// Uses the built-in Components if `components` is None.
 pub fn load_and_register(
     container: HtmlElement,
-    state: SaveState,
+    mut state: SaveState,
     listener: MouseListener,
     document: Option<Document>,
     on_event: Option<ListenerEvent>,
+    components: Option<ComponentRegistry>,
 ) -> Result<ListenerHandle> {
+    state.migrate()?;
+
     let nodes = return_all_text_nodes(&container);
 
-    register_with_data(
-        container,
-        state.into_listener_data(nodes)?,
-        listener,
-        document,
-        on_event,
-    )
+    let data =
+        state.into_listener_data(&container, nodes, Rc::new(components.unwrap_or_default()))?;
+
+    if !data.unplaced.is_empty() {
+        warn!("Unable to place {} saved nodes", data.unplaced.len());
+    }
+
+    register_with_data(container, data, listener, document, on_event)
 }
 
+#[cfg(feature = "frontend")]
 pub fn save(state: &ListenerData) -> SaveState {
     SaveState {
         version: CURRENT_VERSION,
@@ -47,6 +75,49 @@ pub fn save(state: &ListenerData) -> SaveState {
     }
 }
 
+#[cfg(feature = "frontend")]
+/// Same as [`save`] but every node also stores a [`TextAnchor`].
+///
+/// Anchored nodes can still be placed after the HTML inside the `container` changed.
+pub fn save_anchored(state: &ListenerData, container: &Element) -> SaveState {
+    let mut save = save(state);
+
+    let mut text = Vec::new();
+    let mut starts = Vec::new();
+
+    for node in &state.nodes {
+        starts.push(text.len());
+
+        for wrapped in &node.text {
+            text.extend(wrapped.node.data().encode_utf16());
+        }
+    }
+
+    for saved in &mut save.nodes {
+        let node_start = starts[saved.index];
+        let node_end = starts.get(saved.index + 1).copied().unwrap_or(text.len());
+
+        let (Some(first), Some(last)) = (saved.flags.first(), saved.flags.last()) else {
+            continue;
+        };
+
+        let start = node_start + first.offset as usize;
+        let end = last
+            .length
+            .map(|len| node_start + (last.offset + len) as usize)
+            .unwrap_or(node_end);
+
+        let hint = state.nodes[saved.index].text[0]
+            .parent_element()
+            .and_then(|element| anchor::css_path(container, &element))
+            .map(ContainerHint::Css);
+
+        saved.anchor = Some(TextAnchor::new(&text, start..end, hint));
+    }
+
+    save
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SaveState {
     pub version: usize,
@@ -55,93 +126,103 @@ pub struct SaveState {
 }
 
 impl SaveState {
+    /// Upgrades an older `SaveState` to the current version.
+    pub fn migrate(&mut self) -> Result<(), MigrationError> {
+        migration::migrate(self)
+    }
+
+    #[cfg(feature = "frontend")]
     /// Creates the listener tree
-    pub(crate) fn into_listener_data(self, nodes: Vec<Text>) -> Result<ListenerData> {
+    ///
+    /// Nodes which cannot be placed are stored in `ListenerData::unplaced`.
+    pub(crate) fn into_listener_data(
+        self,
+        container: &Element,
+        nodes: Vec<Text>,
+        components: Rc<ComponentRegistry>,
+    ) -> Result<ListenerData> {
         // ListenerId is set in the listener function.
-        let mut listener = ListenerData::new(ListenerId::unset(), nodes)?;
+        let mut listener = ListenerData::new(ListenerId::unset(), nodes.clone(), components)?;
 
         listener.data = self.data;
+        listener.assign_annotation_ids();
+        listener.unplaced = apply_saved_nodes(self.nodes, &mut listener.nodes, |hint| {
+            nodes_in_hint(container, &nodes, hint)
+        })?;
 
-        for saved_node in self.nodes {
-            let list_node = &mut listener.nodes[saved_node.index];
-
-            let mut curr_node = list_node.text[0].node.clone();
-
-            let mut text_offset = 0;
-
-            for text_split in saved_node.flags.iter() {
-                if text_split.offset == 0 {
-                    list_node.set_flag_for_node(
-                        &curr_node,
-                        FlagsWithData::from_singles(&text_split.flags),
-                    )?;
-                } else {
-                    let node = list_node.split_node(&curr_node, text_split.offset - text_offset)?;
-                    list_node
-                        .set_flag_for_node(&node, FlagsWithData::from_singles(&text_split.flags))?;
-                    curr_node = node;
-                }
-
-                // If it contains length (all of them should unless its' selecting until end of Node)
-                // We'll split it, set tag empty, and increase offset since out current node will now be the split node.
-                if let Some(length) = text_split.length {
-                    curr_node = list_node.split_node(&curr_node, length)?;
-                    list_node.set_flag_for_node(&curr_node, FlagsWithData::empty())?;
+        Ok(listener)
+    }
 
-                    text_offset += length;
-                }
+    /// Encodes with [`Encoding::Fixed`].
+    pub fn into_bytes(&self) -> Vec<u8> {
+        self.into_bytes_with(Encoding::Fixed)
+    }
 
-                text_offset += text_split.offset;
-            }
+    pub fn into_bytes_with(&self, encoding: Encoding) -> Vec<u8> {
+        let mut writer = Writer::with_header(encoding);
 
-            // TODO: Determine why I was utilizing this.
-            // list_node.add_flag_to_node(
-            //     &curr_node,
-            //     FlagsWithData::from_singles(&saved_node.flags[0].flags),
-            // )?;
-        }
+        self.write(&mut writer);
 
-        Ok(listener)
+        writer.into_bytes()
     }
 
-    pub fn into_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-
-        bytes.extend_from_slice(&self.version.to_be_bytes());
+    fn write(&self, writer: &mut Writer) {
+        writer.usize(self.version);
 
         // Data
-        bytes.extend_from_slice(&(self.data.len() as u32).to_be_bytes());
+        writer.u32(self.data.len() as u32);
 
         for node in &self.data {
-            bytes.extend_from_slice(&node.0.bits().to_be_bytes());
-            bytes.extend_from_slice(&(node.1.len() as u32).to_be_bytes());
-            bytes.extend_from_slice(node.1.as_bytes());
+            writer.flag(node.0);
+
+            // Annotation ids were added in version 2.
+            if self.version >= 2 {
+                writer.u64(node.2 .0);
+            }
+
+            writer.string(&node.1);
         }
 
         // Nodes
-        bytes.extend_from_slice(&(self.nodes.len() as u32).to_be_bytes());
+        writer.u32(self.nodes.len() as u32);
 
         for node in &self.nodes {
-            bytes.append(&mut node.into_bytes());
+            node.write(writer, self.version);
         }
+    }
 
-        bytes
+    /// Decodes bytes of either [`Encoding`]. Bytes without a header are read as the
+    /// older format written by either a wasm32 or 64-bit build.
+    pub fn from_bytes<B: Buf>(bytes: &mut B) -> Result<Self, DecodeError> {
+        if let Some(mut reader) = Reader::with_header(bytes)? {
+            return Self::read(&mut reader);
+        }
+
+        let legacy = bytes.copy_to_bytes(bytes.remaining());
+
+        // Browsers wrote 4 byte indexes. Anything decoding them fully is the correct width.
+        Self::read(&mut Reader::legacy(&mut legacy.clone(), 4))
+            .or_else(|e| Self::read(&mut Reader::legacy(&mut legacy.clone(), 8)).map_err(|_| e))
     }
 
-    pub fn from_bytes<B: Buf>(bytes: &mut B) -> Self {
-        Self {
-            version: bytes.get_u64() as usize,
+    fn read<B: Buf>(reader: &mut Reader<'_, B>) -> Result<Self, DecodeError> {
+        let version = reader.usize()?;
+
+        let this = Self {
+            version,
             data: {
                 let mut array = Vec::new();
 
-                for _ in 0..bytes.get_u32() {
-                    let flag = ComponentFlag::from_bits_truncate(bytes.get_u32());
+                for _ in 0..reader.u32()? {
+                    let flag = reader.flag()?;
 
-                    let str_len = bytes.get_u32();
-                    let value = bytes.copy_to_bytes(str_len as usize);
-                    let value = String::from_utf8(value.to_vec()).unwrap();
+                    let id = if version >= 2 {
+                        AnnotationId(reader.u64()?)
+                    } else {
+                        AnnotationId::UNSET
+                    };
 
-                    array.push(ComponentDataStore(flag, value));
+                    array.push(ComponentDataStore(flag, reader.string()?, id));
                 }
 
                 array
@@ -149,13 +230,19 @@ impl SaveState {
             nodes: {
                 let mut array = Vec::new();
 
-                for _ in 0..bytes.get_u32() {
-                    array.push(SavedNode::from_bytes(bytes));
+                for _ in 0..reader.u32()? {
+                    array.push(SavedNode::read(reader, version)?);
                 }
 
                 array
             },
+        };
+
+        if reader.remaining() != 0 {
+            return Err(DecodeError::TrailingBytes(reader.remaining()));
         }
+
+        Ok(this)
     }
 }
 
@@ -165,99 +252,213 @@ pub struct SavedNode {
     index: usize,
 
     flags: Vec<SavedNodeFlag>,
+
+    /// Used to find the node again if the HTML has changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) anchor: Option<TextAnchor>,
 }
 
 impl SavedNode {
-    pub(crate) fn from_node(index: usize, components: &[WrappedText]) -> Self {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn anchor(&self) -> Option<&TextAnchor> {
+        self.anchor.as_ref()
+    }
+
+    pub fn flags(&self) -> &[SavedNodeFlag] {
+        &self.flags
+    }
+
+    #[cfg(any(feature = "frontend", feature = "render"))]
+    /// Moves the flags to the `range` the anchor was found at.
+    ///
+    /// `node` is the range the Text Node at `index` covers.
+    ///
+    /// Returns false if the range doesn't fit inside the Text Node.
+    fn move_to(&mut self, index: usize, range: Range<usize>, node: Range<usize>) -> bool {
+        if range.end > node.end || self.flags.is_empty() {
+            return false;
+        }
+
+        let new_start = (range.start - node.start) as i64;
+        let new_end = (range.end - node.start) as u32;
+        let shift = new_start - self.flags[0].offset as i64;
+
+        for flag in &mut self.flags {
+            flag.offset = (flag.offset as i64 + shift) as u32;
+        }
+
+        let last = self.flags.last_mut().unwrap();
+
+        if last.offset >= new_end {
+            return false;
+        }
+
+        last.length = (range.end < node.end).then_some(new_end - last.offset);
+
+        self.index = index;
+
+        true
+    }
+
+    pub fn new(index: usize, flags: Vec<SavedNodeFlag>) -> Self {
         Self {
             index,
-            flags: {
-                let mut flags = Vec::new();
-
-                for i in 0..components.len() {
-                    let comp = &components[i];
-
-                    if !comp.are_flags_empty() {
-                        flags.push(SavedNodeFlag {
-                            offset: comp.offset,
-                            length: components.get(i + 1).map(|v| v.offset - comp.offset),
-                            flags: comp.flag.into_singles_vec(),
-                        })
-                    }
-                }
-
-                flags
-            },
+            flags,
+            anchor: None,
         }
     }
 
+    #[cfg(feature = "frontend")]
+    pub(crate) fn from_node(index: usize, components: &[WrappedText]) -> Self {
+        Self::new(index, SavedNodeFlag::from_wrapped(components))
+    }
+
     pub fn into_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+        let mut writer = Writer::new(Encoding::Fixed);
 
-        bytes.extend_from_slice(&self.index.to_be_bytes());
+        self.write(&mut writer, CURRENT_VERSION);
 
-        bytes.extend_from_slice(&(self.flags.len() as u32).to_be_bytes());
+        writer.into_bytes()
+    }
+
+    fn write(&self, writer: &mut Writer, version: usize) {
+        writer.usize(self.index);
+
+        writer.u32(self.flags.len() as u32);
 
         for flags in &self.flags {
-            bytes.append(&mut flags.into_bytes());
+            flags.write(writer);
         }
 
-        bytes
+        // Anchors were added in version 1.
+        if version >= 1 {
+            if let Some(anchor) = &self.anchor {
+                writer.u8(1);
+                write_anchor(anchor, writer);
+            } else {
+                writer.u8(0);
+            }
+        }
     }
 
-    pub fn from_bytes<B: Buf>(bytes: &mut B) -> Self {
-        Self {
-            index: bytes.get_u64() as usize,
+    pub fn from_bytes<B: Buf>(bytes: &mut B) -> Result<Self, DecodeError> {
+        Self::read(&mut Reader::new(bytes, Encoding::Fixed), CURRENT_VERSION)
+    }
+
+    fn read<B: Buf>(reader: &mut Reader<'_, B>, version: usize) -> Result<Self, DecodeError> {
+        Ok(Self {
+            index: reader.usize()?,
             flags: {
                 let mut array = Vec::new();
 
-                for _ in 0..bytes.get_u32() {
-                    array.push(SavedNodeFlag::from_bytes(bytes));
+                for _ in 0..reader.u32()? {
+                    array.push(SavedNodeFlag::read(reader)?);
                 }
 
                 array
             },
-        }
+            anchor: {
+                if version >= 1 && reader.u8()? == 1 {
+                    Some(read_anchor(reader)?)
+                } else {
+                    None
+                }
+            },
+        })
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SavedNodeFlag {
-    offset: u32,
-    length: Option<u32>,
+    pub(crate) offset: u32,
+    pub(crate) length: Option<u32>,
     // TODO: Should I change to vec with SingleFlagWithData?
-    flags: Vec<SingleFlagWithData>,
+    pub(crate) flags: Vec<SingleFlagWithData>,
 }
 
 impl SavedNodeFlag {
+    /// Returns the flagged sections of the split Text Node.
+    pub(crate) fn from_wrapped<D: Dom>(components: &[WrappedText<D>]) -> Vec<Self> {
+        Self::from_wrapped_with(components, |v| Some(&v.flag))
+    }
+
+    /// Same as [`SavedNodeFlag::from_wrapped`] using the flags returned by `get_flag`.
+    ///
+    /// Sections next to each other with the same flags are merged.
+    pub(crate) fn from_wrapped_with<'a, D: Dom>(
+        components: &'a [WrappedText<D>],
+        get_flag: impl Fn(&'a WrappedText<D>) -> Option<&'a FlagsWithData>,
+    ) -> Vec<Self> {
+        let mut flags = Vec::<Self>::new();
+
+        for i in 0..components.len() {
+            let comp = &components[i];
+
+            let Some(flag) = get_flag(comp).filter(|v| !v.is_empty()) else {
+                continue;
+            };
+
+            let length = components.get(i + 1).map(|v| v.offset - comp.offset);
+            let singles = flag.into_singles_vec();
+
+            match flags.last_mut() {
+                Some(last)
+                    if last.flags == singles
+                        && last.length.map(|v| last.offset + v) == Some(comp.offset) =>
+                {
+                    last.length = length.map(|v| comp.offset + v - last.offset);
+                }
+
+                _ => flags.push(SavedNodeFlag {
+                    offset: comp.offset,
+                    length,
+                    flags: singles,
+                }),
+            }
+        }
+
+        flags
+    }
+
     pub fn into_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+        let mut writer = Writer::new(Encoding::Fixed);
 
-        bytes.extend_from_slice(&self.offset.to_be_bytes());
+        self.write(&mut writer);
+
+        writer.into_bytes()
+    }
+
+    fn write(&self, writer: &mut Writer) {
+        writer.u32(self.offset);
 
         if let Some(len) = self.length {
-            bytes.push(1);
-            bytes.extend_from_slice(&len.to_be_bytes());
+            writer.u8(1);
+            writer.u32(len);
         } else {
-            bytes.push(0);
+            writer.u8(0);
         }
 
-        // We should never have a length greater than 255.
-        bytes.push(self.flags.len() as u8);
+        writer.u32(self.flags.len() as u32);
 
         for flags in &self.flags {
-            bytes.extend_from_slice(&flags.0.to_be_bytes());
+            writer.flag(flags.flag());
+            writer.u32(flags.data());
         }
+    }
 
-        bytes
+    pub fn from_bytes<B: Buf>(bytes: &mut B) -> Result<Self, DecodeError> {
+        Self::read(&mut Reader::new(bytes, Encoding::Fixed))
     }
 
-    pub fn from_bytes<B: Buf>(bytes: &mut B) -> Self {
-        Self {
-            offset: bytes.get_u32(),
+    fn read<B: Buf>(reader: &mut Reader<'_, B>) -> Result<Self, DecodeError> {
+        Ok(Self {
+            offset: reader.u32()?,
             length: {
-                if bytes.get_u8() == 1 {
-                    Some(bytes.get_u32())
+                if reader.u8()? == 1 {
+                    Some(reader.u32()?)
                 } else {
                     None
                 }
@@ -265,21 +466,206 @@ impl SavedNodeFlag {
             flags: {
                 let mut array = Vec::new();
 
-                for _ in 0..bytes.get_u8() {
-                    array.push(SingleFlagWithData(bytes.get_u64()));
+                // Format 1 and older wrote the count as a single byte.
+                let count = if reader.format() >= 2 {
+                    reader.u32()?
+                } else {
+                    reader.u8()? as u32
+                };
+
+                for _ in 0..count {
+                    let flag = reader.flag()?;
+                    let data = reader.u32()?;
+
+                    array.push(SingleFlagWithData::new(flag, data));
                 }
 
                 array
             },
+        })
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("Unexpected end of bytes. Needed {needed} more but only {remaining} remain")]
+    Truncated { needed: usize, remaining: usize },
+
+    #[error("Invalid UTF-8 in Component Data: {0}")]
+    InvalidUtf8(#[from] std::string::FromUtf8Error),
+
+    #[error("Unknown Component Flag bits: {0:#b}")]
+    UnknownFlag(u32),
+
+    #[error("Invalid tag byte: {0}")]
+    InvalidTag(u8),
+
+    #[error("{0} trailing bytes after the Save State")]
+    TrailingBytes(usize),
+
+    #[error("Varint is longer than 64 bits")]
+    InvalidVarint,
+
+    #[error("Value {0} doesn't fit into its' field")]
+    Overflow(u64),
+
+    #[error("Unsupported binary format version: {0}")]
+    UnsupportedFormat(u8),
+}
+
+#[cfg(any(feature = "frontend", feature = "render"))]
+/// Splits the Text Containers at the saved nodes. The same way for every [`Dom`].
+///
+/// Returns the saved nodes which couldn't be placed.
+pub(crate) fn apply_saved_nodes<D: Dom>(
+    saved: Vec<SavedNode>,
+    containers: &mut [TextContainer<D>],
+    nodes_in_hint: impl FnMut(&ContainerHint) -> Result<Option<RangeInclusive<usize>>, D::Error>,
+) -> Result<Vec<SavedNode>, D::Error> {
+    let texts = containers
+        .iter()
+        .map(TextContainer::text_content)
+        .collect::<Vec<_>>();
+
+    let (placed, unplaced) = place_saved_nodes(saved, &texts, nodes_in_hint);
+
+    for saved_node in placed {
+        containers[saved_node.index].apply_saved_flags(&saved_node.flags)?;
+    }
+
+    Ok(unplaced)
+}
+
+#[cfg(feature = "frontend")]
+/// The indexes of the first and last Text Node inside of the Element the hint points to.
+pub(crate) fn nodes_in_hint(
+    container: &Element,
+    nodes: &[Text],
+    hint: &ContainerHint,
+) -> Result<Option<RangeInclusive<usize>>> {
+    Ok(anchor::resolve_hint(container, hint)?.and_then(|element| {
+        let first = nodes.iter().position(|v| element.contains(Some(v)))?;
+        let last = nodes.iter().rposition(|v| element.contains(Some(v)))?;
+
+        Some(first..=last)
+    }))
+}
+
+#[cfg(any(feature = "frontend", feature = "render"))]
+/// Separates the saved nodes into the ones we can place and the ones we can't.
+///
+/// `nodes` is the text of each node index. `nodes_in_hint` returns the node indexes inside of
+/// the Element a container hint points to. A hint which can't be resolved searches the whole text.
+/// Anchored nodes are moved to wherever their text is found now.
+pub(crate) fn place_saved_nodes<E: std::fmt::Debug>(
+    saved: Vec<SavedNode>,
+    nodes: &[String],
+    mut nodes_in_hint: impl FnMut(&ContainerHint) -> Result<Option<RangeInclusive<usize>>, E>,
+) -> (Vec<SavedNode>, Vec<SavedNode>) {
+    let mut text = Vec::new();
+    let mut starts = Vec::new();
+
+    for value in nodes {
+        starts.push(text.len());
+        text.extend(value.encode_utf16());
+    }
+
+    let node_range =
+        |index: usize| starts[index]..starts.get(index + 1).copied().unwrap_or(text.len());
+
+    let mut placed = Vec::new();
+    let mut unplaced = Vec::new();
+
+    for mut saved_node in saved {
+        let Some(anchor) = saved_node.anchor.clone() else {
+            if saved_node.index < nodes.len() {
+                placed.push(saved_node);
+            } else {
+                unplaced.push(saved_node);
+            }
+
+            continue;
+        };
+
+        let within = match anchor.container.as_ref().map(&mut nodes_in_hint) {
+            Some(Ok(nodes)) => {
+                nodes.map(|nodes| starts[*nodes.start()]..node_range(*nodes.end()).end)
+            }
+            Some(Err(e)) => {
+                warn!("Unable to resolve container hint: {e:?}");
+                None
+            }
+            None => None,
+        };
+
+        // Only search the whole text again if the first search was narrowed down.
+        let found = within
+            .and_then(|within| anchor.locate(&text, Some(within)))
+            .or_else(|| anchor.locate(&text, None));
+
+        let was_placed = found.is_some_and(|range| {
+            // Last node which starts at or before the range.
+            let index = starts.partition_point(|&v| v <= range.start) - 1;
+
+            saved_node.move_to(index, range, node_range(index))
+        });
+
+        if was_placed {
+            placed.push(saved_node);
+        } else {
+            unplaced.push(saved_node);
+        }
+    }
+
+    (placed, unplaced)
+}
+
+fn write_anchor(anchor: &TextAnchor, writer: &mut Writer) {
+    writer.string(&anchor.quote.exact);
+    writer.string(&anchor.quote.prefix);
+    writer.string(&anchor.quote.suffix);
+
+    writer.u32(anchor.position.start);
+    writer.u32(anchor.position.end);
+
+    match &anchor.container {
+        None => writer.u8(0),
+        Some(ContainerHint::Css(value)) => {
+            writer.u8(1);
+            writer.string(value);
+        }
+        Some(ContainerHint::XPath(value)) => {
+            writer.u8(2);
+            writer.string(value);
         }
     }
 }
 
+fn read_anchor<B: Buf>(reader: &mut Reader<'_, B>) -> Result<TextAnchor, DecodeError> {
+    Ok(TextAnchor {
+        quote: TextQuoteSelector {
+            exact: reader.string()?,
+            prefix: reader.string()?,
+            suffix: reader.string()?,
+        },
+        position: TextPositionSelector {
+            start: reader.u32()?,
+            end: reader.u32()?,
+        },
+        container: match reader.u8()? {
+            0 => None,
+            1 => Some(ContainerHint::Css(reader.string()?)),
+            2 => Some(ContainerHint::XPath(reader.string()?)),
+            v => return Err(DecodeError::InvalidTag(v)),
+        },
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::Bytes;
 
-    use crate::ComponentFlag;
+    use crate::{component::FlagsWithData, ComponentFlag};
 
     use super::*;
 
@@ -293,7 +679,7 @@ mod tests {
 
         let bytes = save.into_bytes();
 
-        let save2 = SavedNodeFlag::from_bytes(&mut Bytes::from(bytes));
+        let save2 = SavedNodeFlag::from_bytes(&mut Bytes::from(bytes)).unwrap();
 
         assert_eq!(save, save2);
     }
@@ -307,11 +693,23 @@ mod tests {
                 length: Some(5678),
                 flags: vec![SingleFlagWithData::new(ComponentFlag::ITALICIZE, 11)],
             }],
+            anchor: Some(TextAnchor {
+                quote: TextQuoteSelector {
+                    exact: String::from("exact"),
+                    prefix: String::from("prefix"),
+                    suffix: String::from("suffix"),
+                },
+                position: TextPositionSelector {
+                    start: 1234,
+                    end: 6912,
+                },
+                container: Some(ContainerHint::Css(String::from(":scope > p:nth-child(2)"))),
+            }),
         };
 
         let bytes = save.into_bytes();
 
-        let save2 = SavedNode::from_bytes(&mut Bytes::from(bytes));
+        let save2 = SavedNode::from_bytes(&mut Bytes::from(bytes)).unwrap();
 
         assert_eq!(save, save2);
     }
@@ -328,13 +726,317 @@ mod tests {
                     length: Some(5678),
                     flags: vec![SingleFlagWithData::new(ComponentFlag::ITALICIZE, 11)],
                 }],
+                anchor: None,
             }],
         };
 
         let bytes = save.into_bytes();
 
-        let save2 = SaveState::from_bytes(&mut Bytes::from(bytes));
+        let save2 = SaveState::from_bytes(&mut Bytes::from(bytes)).unwrap();
+
+        assert_eq!(save, save2);
+    }
+
+    #[test]
+    fn overlapping_flags_to_from_bytes() {
+        let mut flags = FlagsWithData::new_with_data(ComponentFlag::NOTE, 0);
+        flags.insert(FlagsWithData::new_with_data(ComponentFlag::NOTE, 2));
+        flags.insert(FlagsWithData::new_with_data(ComponentFlag::BOLD, 0));
+
+        let save = SavedNodeFlag {
+            offset: 3,
+            length: Some(7),
+            flags: flags.into_singles_vec(),
+        };
+
+        assert_eq!(save.flags.len(), 3);
+
+        let save2 = SavedNodeFlag::from_bytes(&mut Bytes::from(save.into_bytes())).unwrap();
 
         assert_eq!(save, save2);
+        assert_eq!(FlagsWithData::from_singles(&save2.flags), flags);
+
+        // Removing one note keeps the other.
+        flags.remove(&FlagsWithData::new_with_data(ComponentFlag::NOTE, 0));
+        assert_eq!(flags.data_of(ComponentFlag::NOTE).collect::<Vec<_>>(), [2]);
+
+        flags.remove(&FlagsWithData::new_with_data(ComponentFlag::NOTE, 2));
+        assert!(!flags.intersects_flag(ComponentFlag::NOTE));
+    }
+
+    #[test]
+    fn save_state_annotation_ids() {
+        let mut save = save_state();
+        save.version = CURRENT_VERSION;
+        save.data[0].2 = AnnotationId(42);
+
+        let save2 = SaveState::from_bytes(&mut Bytes::from(save.into_bytes())).unwrap();
+
+        assert_eq!(save2.data[0].id(), AnnotationId(42));
+
+        // Older states don't store the ids.
+        let mut json = serde_json::to_value(&save).unwrap();
+        json["version"] = 1.into();
+        json["data"][0].as_array_mut().unwrap().pop();
+
+        let old = serde_json::from_value::<SaveState>(json).unwrap();
+
+        assert!(old.data[0].id().is_unset());
+        assert_eq!(old.data[0].parse::<String>(), "note");
+
+        #[cfg(feature = "migrations")]
+        {
+            let mut old = old;
+            old.migrate().unwrap();
+            assert_eq!(old.data[0].id(), AnnotationId(1));
+        }
+    }
+
+    fn save_state() -> SaveState {
+        SaveState {
+            version: 0,
+            data: vec![ComponentDataStore::new(ComponentFlag::NOTE, &"note")],
+            nodes: vec![SavedNode {
+                index: 0,
+                flags: vec![SavedNodeFlag {
+                    offset: 0,
+                    length: None,
+                    flags: vec![SingleFlagWithData::new(ComponentFlag::NOTE, 0)],
+                }],
+                anchor: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn save_state_version_0_from_bytes() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0u64.to_be_bytes());
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+        bytes.append(&mut legacy_node_bytes(&save_state().nodes[0], 0, 8));
+
+        let save = SaveState::from_bytes(&mut Bytes::from(bytes)).unwrap();
+
+        assert_eq!(save.version, 0);
+        assert_eq!(save.nodes, save_state().nodes);
+    }
+
+    /// A node without an anchor as written before the header existed.
+    fn legacy_node_bytes(node: &SavedNode, version: usize, index_width: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(node.index as u64).to_be_bytes()[8 - index_width..]);
+        bytes.extend_from_slice(&(node.flags.len() as u32).to_be_bytes());
+
+        for flag in &node.flags {
+            bytes.extend_from_slice(&flag.offset.to_be_bytes());
+
+            if let Some(length) = flag.length {
+                bytes.push(1);
+                bytes.extend_from_slice(&length.to_be_bytes());
+            } else {
+                bytes.push(0);
+            }
+
+            bytes.push(flag.flags.len() as u8);
+
+            for single in &flag.flags {
+                let packed = (single.flag().bits() as u64) << 32 | single.data() as u64;
+                bytes.extend_from_slice(&packed.to_be_bytes());
+            }
+        }
+
+        if version >= 1 {
+            bytes.push(0);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn save_state_compact() {
+        let mut save = save_state();
+        save.version = CURRENT_VERSION;
+        save.data[0].2 = AnnotationId(1);
+        save.nodes[0].flags[0].length = Some(300);
+
+        let fixed = save.into_bytes();
+        let compact = save.into_bytes_with(Encoding::Compact);
+
+        assert!(compact.len() < fixed.len());
+        assert_eq!(SaveState::from_bytes(&mut Bytes::from(compact)), Ok(save));
+    }
+
+    #[test]
+    fn save_state_legacy_from_bytes() {
+        let mut save = save_state();
+        save.version = 1;
+        save.nodes[0].index = 3;
+
+        // Written by the old encoder on wasm32 where `usize` is 4 bytes, then on 64-bit.
+        for width in [4, 8] {
+            let mut bytes = Vec::new();
+            bytes.extend_from_slice(&1u64.to_be_bytes()[8 - width..]);
+            bytes.extend_from_slice(&1u32.to_be_bytes());
+            bytes.extend_from_slice(&ComponentFlag::NOTE.bits().to_be_bytes());
+            bytes.extend_from_slice(&6u32.to_be_bytes());
+            bytes.extend_from_slice(b"\"note\"");
+            bytes.extend_from_slice(&1u32.to_be_bytes());
+            bytes.append(&mut legacy_node_bytes(&save.nodes[0], 1, width));
+
+            assert_eq!(
+                SaveState::from_bytes(&mut Bytes::from(bytes)),
+                Ok(save.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn data_ids_past_255() {
+        let mut save = save_state();
+        save.version = CURRENT_VERSION;
+        save.nodes[0].flags[0].flags = vec![
+            SingleFlagWithData::new(ComponentFlag::NOTE, 256),
+            SingleFlagWithData::new(ComponentFlag::custom(15), u32::MAX),
+        ];
+
+        for encoding in [Encoding::Fixed, Encoding::Compact] {
+            let bytes = save.into_bytes_with(encoding);
+            assert_eq!(
+                SaveState::from_bytes(&mut Bytes::from(bytes)),
+                Ok(save.clone())
+            );
+        }
+
+        let json = serde_json::to_string(&save).unwrap();
+        assert_eq!(serde_json::from_str::<SaveState>(&json).unwrap(), save);
+
+        // Older states packed both into a single number.
+        let packed = (ComponentFlag::NOTE.bits() as u64) << 32 | 256;
+        assert_eq!(
+            serde_json::from_str::<SingleFlagWithData>(&packed.to_string()).unwrap(),
+            SingleFlagWithData::new(ComponentFlag::NOTE, 256)
+        );
+
+        assert!(serde_json::from_str::<SingleFlagWithData>("[8, 4294967296]").is_err());
+        assert!(serde_json::from_str::<SingleFlagWithData>(&(1u64 << 42).to_string()).is_err());
+    }
+
+    #[cfg(any(feature = "frontend", feature = "render"))]
+    #[test]
+    fn saved_node_move_to() {
+        let mut node = SavedNode {
+            index: 0,
+            flags: vec![
+                SavedNodeFlag {
+                    offset: 2,
+                    length: Some(3),
+                    flags: vec![SingleFlagWithData::new(ComponentFlag::HIGHLIGHT, 0)],
+                },
+                SavedNodeFlag {
+                    offset: 5,
+                    length: None,
+                    flags: vec![SingleFlagWithData::new(ComponentFlag::UNDERLINE, 0)],
+                },
+            ],
+            anchor: None,
+        };
+
+        // Text was moved 10 units into the next node and now ends before the node does.
+        assert!(node.move_to(1, 30..38, 20..50));
+
+        assert_eq!(node.index, 1);
+        assert_eq!((node.flags[0].offset, node.flags[0].length), (10, Some(3)));
+        assert_eq!((node.flags[1].offset, node.flags[1].length), (13, Some(5)));
+
+        // Spans past the end of the node.
+        assert!(!node.move_to(1, 45..55, 20..50));
+    }
+
+    #[cfg(any(feature = "frontend", feature = "render"))]
+    #[test]
+    fn place_with_malformed_hint() {
+        let nodes = vec![String::from("Same"), String::from("Same")];
+        let text = "SameSame".encode_utf16().collect::<Vec<_>>();
+
+        let mut anchored = SavedNode::new(
+            0,
+            vec![SavedNodeFlag {
+                offset: 0,
+                length: None,
+                flags: vec![SingleFlagWithData::new(ComponentFlag::UNDERLINE, 0)],
+            }],
+        );
+        anchored.anchor = Some(TextAnchor::new(
+            &text,
+            4..8,
+            Some(ContainerHint::Css(String::from(":scope > [[p"))),
+        ));
+
+        let (placed, unplaced) = place_saved_nodes(
+            vec![SavedNode::new(1, Vec::new()), anchored],
+            &nodes,
+            |_| Err("Invalid selector"),
+        );
+
+        // Falls back to searching the whole text instead of failing.
+        assert!(unplaced.is_empty());
+        assert_eq!(
+            placed.iter().map(|v| v.index).collect::<Vec<_>>(),
+            vec![1, 1]
+        );
+    }
+
+    #[test]
+    fn save_state_truncated() {
+        let mut bytes = save_state().into_bytes();
+        bytes.pop();
+
+        assert!(matches!(
+            SaveState::from_bytes(&mut Bytes::from(bytes)),
+            Err(DecodeError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn save_state_trailing_bytes() {
+        let mut bytes = save_state().into_bytes();
+        bytes.extend_from_slice(&[0, 0]);
+
+        assert_eq!(
+            SaveState::from_bytes(&mut Bytes::from(bytes)),
+            Err(DecodeError::TrailingBytes(2))
+        );
+    }
+
+    #[test]
+    fn save_state_invalid_utf8() {
+        let mut bytes = save_state().into_bytes();
+        // First byte of the Note data string:
+        // header (6) + version (8) + data count (4) + flag (4) + length (4).
+        bytes[26] = 0xFF;
+
+        assert!(matches!(
+            SaveState::from_bytes(&mut Bytes::from(bytes)),
+            Err(DecodeError::InvalidUtf8(_))
+        ));
+    }
+
+    #[test]
+    fn save_node_flag_unknown_flag() {
+        let mut bytes = SavedNodeFlag {
+            offset: 0,
+            length: None,
+            flags: Vec::new(),
+        }
+        .into_bytes();
+
+        *bytes.last_mut().unwrap() = 1;
+        bytes.extend_from_slice(&(1u64 << 42).to_be_bytes());
+
+        assert_eq!(
+            SavedNodeFlag::from_bytes(&mut Bytes::from(bytes)),
+            Err(DecodeError::UnknownFlag(1 << 10))
+        );
     }
 }
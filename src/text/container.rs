@@ -1,26 +1,36 @@
-use web_sys::Text;
+use std::rc::Rc;
 
-use crate::{component::FlagsWithData, ComponentFlag, Result, WrappedText};
+use crate::{
+    component::{ComponentRegistry, FlagsWithData},
+    dom::{DefaultDom, Dom},
+    layer::LayerId,
+    store::SavedNodeFlag,
+    ComponentFlag, WrappedText,
+};
 
 /// Contains the Text Node we can split apart into smaller ones.
 ///
 /// We use this struct to better show that if there are multiple items in the vec that means we have split it apart.
-#[derive(Debug, Clone)]
-pub struct TextContainer {
+pub struct TextContainer<D: Dom = DefaultDom> {
     /// The non-split Text `Node` or split `Node`s
-    pub(crate) text: Vec<WrappedText>,
+    pub(crate) text: Vec<WrappedText<D>>,
 }
 
-impl TextContainer {
-    pub fn new(text: Text) -> Result<Self> {
+impl<D: Dom> TextContainer<D> {
+    pub fn new(text: D::Text, components: Rc<ComponentRegistry>) -> Result<Self, D::Error> {
         Ok(Self {
-            text: vec![WrappedText::wrap(text, 0, FlagsWithData::empty())?],
+            text: vec![WrappedText::wrap(
+                text,
+                0,
+                FlagsWithData::empty(),
+                components,
+            )?],
         })
     }
 
-    pub fn get_by_text_index(&mut self, index: u32) -> Option<(u32, Text)> {
+    pub fn get_by_text_index(&mut self, index: u32) -> Option<(u32, D::Text)> {
         self.text.iter().find_map(|v| {
-            if index < v.offset + v.node.length() {
+            if index < v.offset + D::text_length(&v.node) {
                 Some((index - v.offset, v.node.clone()))
             } else {
                 None
@@ -44,19 +54,27 @@ impl TextContainer {
         self.text.iter().all(|v| v.are_flags_empty())
     }
 
-    pub fn contains_node(&self, node: &Text) -> bool {
+    /// The text of every split Text Node.
+    pub fn text_content(&self) -> String {
+        self.text.iter().map(|v| D::text_data(&v.node)).collect()
+    }
+
+    pub fn contains_node(&self, node: &D::Text) -> bool {
         self.text.iter().any(|v| &v.node == node)
     }
 
-    pub fn get_wrapped_text(&self, node: &Text) -> Option<&WrappedText> {
+    pub fn get_wrapped_text(&self, node: &D::Text) -> Option<&WrappedText<D>> {
         self.text.iter().find(|v| &v.node == node)
     }
 
-    pub fn get_wrapped_text_mut(&mut self, node: &Text) -> Option<&mut WrappedText> {
+    pub fn get_wrapped_text_mut(&mut self, node: &D::Text) -> Option<&mut WrappedText<D>> {
         self.text.iter_mut().find(|v| &v.node == node)
     }
 
-    pub fn find_node_return_mut_ref(&mut self, node: &Text) -> Option<FoundWrappedTextRefMut<'_>> {
+    pub fn find_node_return_mut_ref(
+        &mut self,
+        node: &D::Text,
+    ) -> Option<FoundWrappedTextRefMut<'_, D>> {
         let node_index = self.text.iter().position(|v| &v.node == node)?;
 
         Some(FoundWrappedTextRefMut {
@@ -65,7 +83,11 @@ impl TextContainer {
         })
     }
 
-    pub fn add_flag_to_node(&mut self, node: &Text, flag: FlagsWithData) -> Result<()> {
+    pub fn add_flag_to_node(
+        &mut self,
+        node: &D::Text,
+        flag: FlagsWithData,
+    ) -> Result<(), D::Error> {
         if let Some((index, comp)) = self
             .text
             .iter_mut()
@@ -80,7 +102,11 @@ impl TextContainer {
         Ok(())
     }
 
-    pub fn set_flag_for_node(&mut self, node: &Text, flag: FlagsWithData) -> Result<()> {
+    pub fn set_flag_for_node(
+        &mut self,
+        node: &D::Text,
+        flag: FlagsWithData,
+    ) -> Result<(), D::Error> {
         if let Some((index, comp)) = self
             .text
             .iter_mut()
@@ -95,7 +121,11 @@ impl TextContainer {
         Ok(())
     }
 
-    pub fn remove_flag_from_node(&mut self, node: &Text, flag: &FlagsWithData) -> Result<()> {
+    pub fn remove_flag_from_node(
+        &mut self,
+        node: &D::Text,
+        flag: &FlagsWithData,
+    ) -> Result<(), D::Error> {
         if let Some((index, comp)) = self
             .text
             .iter_mut()
@@ -110,10 +140,152 @@ impl TextContainer {
         Ok(())
     }
 
+    /// Returns the flagged sections of the Text Node.
+    pub fn saved_flags(&self) -> Vec<SavedNodeFlag> {
+        SavedNodeFlag::from_wrapped(&self.text)
+    }
+
+    /// Splits the Text Node apart and sets the flags for each saved section.
+    ///
+    /// Expects the Text Node to not be split yet.
+    pub fn apply_saved_flags(&mut self, flags: &[SavedNodeFlag]) -> Result<(), D::Error> {
+        let mut curr_node = self.text[0].node.clone();
+
+        let mut text_offset = 0;
+
+        for text_split in flags {
+            if text_split.offset != text_offset {
+                curr_node = self.split_node(&curr_node, text_split.offset - text_offset)?;
+                text_offset = text_split.offset;
+            }
+
+            self.set_flag_for_node(&curr_node, FlagsWithData::from_singles(&text_split.flags))?;
+
+            // If it contains length (all of them should unless its' selecting until end of Node)
+            // We'll split it, set tag empty, and increase offset since out current node will now be the split node.
+            if let Some(length) = text_split.length {
+                curr_node = self.split_node(&curr_node, length)?;
+                self.set_flag_for_node(&curr_node, FlagsWithData::empty())?;
+
+                text_offset += length;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the current flags with the saved ones.
+    pub fn set_saved_flags(&mut self, flags: &[SavedNodeFlag]) -> Result<(), D::Error> {
+        let layers = self.saved_layer_flags();
+
+        for text in &mut self.text {
+            if !text.are_flags_empty() {
+                text.remove_all_flag()?;
+            }
+
+            if !text.layers.is_empty() {
+                text.remove_all_layers()?;
+            }
+        }
+
+        // Join the Text Node back together.
+        while self.text.len() > 1 {
+            let next = self.text.remove(1);
+            self.text[0].join(next)?;
+        }
+
+        self.apply_saved_flags(flags)?;
+
+        for (layer, flags) in layers {
+            self.set_layer_flags(layer, &flags)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the flagged sections of every layer.
+    fn saved_layer_flags(&self) -> Vec<(LayerId, Vec<SavedNodeFlag>)> {
+        let mut layers = self
+            .text
+            .iter()
+            .flat_map(|v| v.layers.iter().map(|(id, _)| *id))
+            .collect::<Vec<_>>();
+
+        layers.sort_unstable();
+        layers.dedup();
+
+        layers
+            .into_iter()
+            .map(|layer| {
+                (
+                    layer,
+                    SavedNodeFlag::from_wrapped_with(&self.text, |v| v.layer_flag(layer)),
+                )
+            })
+            .collect()
+    }
+
+    /// Replaces the flags of the layer with the saved ones.
+    pub fn set_layer_flags(
+        &mut self,
+        layer: LayerId,
+        flags: &[SavedNodeFlag],
+    ) -> Result<(), D::Error> {
+        for text in &mut self.text {
+            if text.layer_flag(layer).is_some() {
+                text.set_layer_flag(layer, FlagsWithData::empty())?;
+            }
+        }
+
+        for saved in flags {
+            let start = self.split_at(saved.offset)?;
+            let end = match saved.length {
+                Some(length) => self.split_at(saved.offset + length)?,
+                None => self.text.len(),
+            };
+
+            for text in &mut self.text[start..end] {
+                text.set_layer_flag(layer, FlagsWithData::from_singles(&saved.flags))?;
+            }
+        }
+
+        self.rejoin_all()
+    }
+
+    /// Splits the Text so a `WrappedText` starts at the offset. Returns its' index.
+    fn split_at(&mut self, offset: u32) -> Result<usize, D::Error> {
+        for (index, item) in self.text.iter().enumerate() {
+            if item.offset == offset {
+                return Ok(index);
+            }
+
+            if offset < item.offset + D::text_length(&item.node) {
+                let node = item.node.clone();
+                self.split_node(&node, offset - item.offset)?;
+
+                return Ok(index + 1);
+            }
+        }
+
+        Ok(self.text.len())
+    }
+
+    /// Joins every `WrappedText` with the same flags as the one before it.
+    fn rejoin_all(&mut self) -> Result<(), D::Error> {
+        for index in (1..self.text.len()).rev() {
+            if self.text[index].same_flags(&self.text[index - 1]) {
+                let curr = self.text.remove(index);
+                self.text[index - 1].join(curr)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Splits and inserts the new ComponentNode in the correct position in the array.
     ///
     /// Returns the new, split node.
-    pub fn split_node(&mut self, node: &Text, index: u32) -> Result<Text> {
+    pub fn split_node(&mut self, node: &D::Text, index: u32) -> Result<D::Text, D::Error> {
         for (i, item) in self.text.iter().enumerate() {
             if &item.node == node {
                 let comp = item.split(index)?;
@@ -129,15 +301,33 @@ impl TextContainer {
     }
 }
 
-impl Drop for TextContainer {
+// Derived impls would require `D` itself to implement them.
+impl<D: Dom> Clone for TextContainer<D> {
+    fn clone(&self) -> Self {
+        Self {
+            text: self.text.clone(),
+        }
+    }
+}
+
+impl<D: Dom> std::fmt::Debug for TextContainer<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextContainer")
+            .field("text", &self.text)
+            .finish()
+    }
+}
+
+impl<D: Dom> Drop for TextContainer<D> {
     fn drop(&mut self) {
         while let Some((index, comp)) = self
             .text
             .iter_mut()
             .enumerate()
-            .find(|(_, v)| !v.are_flags_empty())
+            .find(|(_, v)| !v.is_plain())
         {
             let _ = comp.remove_all_flag().map_err(|e| error!("{e:?}"));
+            let _ = comp.remove_all_layers().map_err(|e| error!("{e:?}"));
 
             let _ = try_join_component_into_surroundings(index, &mut self.text)
                 .map_err(|e| error!("{e:?}"));
@@ -146,46 +336,46 @@ impl Drop for TextContainer {
 }
 
 /// Simple struct for finding a `WrappedText` while also allowing for container access if needed.
-pub struct FoundWrappedTextRefMut<'a> {
-    container: &'a mut TextContainer,
+pub struct FoundWrappedTextRefMut<'a, D: Dom = DefaultDom> {
+    container: &'a mut TextContainer<D>,
     node_index: usize,
 }
 
-impl<'a> FoundWrappedTextRefMut<'a> {
-    pub fn get_text_mut(&mut self) -> &mut WrappedText {
+impl<'a, D: Dom> FoundWrappedTextRefMut<'a, D> {
+    pub fn get_text_mut(&mut self) -> &mut WrappedText<D> {
         &mut self.container.text[self.node_index]
     }
 
-    pub fn add_flag_to(&mut self, flag: FlagsWithData) -> Result<()> {
+    pub fn add_flag_to(&mut self, flag: FlagsWithData) -> Result<(), D::Error> {
         self.get_text_mut().add_flag(flag)
     }
 
-    pub fn set_flag_for(&mut self, flag: FlagsWithData) -> Result<()> {
+    pub fn set_flag_for(&mut self, flag: FlagsWithData) -> Result<(), D::Error> {
         self.get_text_mut().set_flag(flag)
     }
 
-    pub fn remove_flag_from(&mut self, flag: &FlagsWithData) -> Result<()> {
+    pub fn remove_flag_from(&mut self, flag: &FlagsWithData) -> Result<(), D::Error> {
         self.get_text_mut().remove_flag(flag)
     }
 
-    pub fn empty_flags_from(&mut self) -> Result<()> {
+    pub fn empty_flags_from(&mut self) -> Result<(), D::Error> {
         self.get_text_mut().remove_all_flag()
     }
 
-    pub fn rejoin_into_surrounding(&mut self) -> Result<()> {
+    pub fn rejoin_into_surrounding(&mut self) -> Result<(), D::Error> {
         try_join_component_into_surroundings(self.node_index, &mut self.container.text)
     }
 }
 
-impl<'a> std::ops::Deref for FoundWrappedTextRefMut<'a> {
-    type Target = TextContainer;
+impl<'a, D: Dom> std::ops::Deref for FoundWrappedTextRefMut<'a, D> {
+    type Target = TextContainer<D>;
 
     fn deref(&self) -> &Self::Target {
         &*self.container
     }
 }
 
-impl<'a> std::ops::DerefMut for FoundWrappedTextRefMut<'a> {
+impl<'a, D: Dom> std::ops::DerefMut for FoundWrappedTextRefMut<'a, D> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.container
     }
@@ -194,14 +384,14 @@ impl<'a> std::ops::DerefMut for FoundWrappedTextRefMut<'a> {
 /// Find and join Component Nodes' of the same type.
 ///
 /// This function will recursively call itself until no more nodes can be joined.
-fn try_join_component_into_surroundings(
+fn try_join_component_into_surroundings<D: Dom>(
     mut index: usize,
-    nodes: &mut Vec<WrappedText>,
-) -> Result<()> {
+    nodes: &mut Vec<WrappedText<D>>,
+) -> Result<(), D::Error> {
     let mut joined = false;
 
     // Compare current and previous component.
-    if index != 0 && nodes[index].flag == nodes[index - 1].flag {
+    if index != 0 && nodes[index].same_flags(&nodes[index - 1]) {
         let curr = nodes.remove(index);
 
         nodes[index - 1].join(curr)?;
@@ -212,7 +402,7 @@ fn try_join_component_into_surroundings(
     }
 
     // Compare current and next component.
-    if index + 1 < nodes.len() && nodes[index].flag == nodes[index + 1].flag {
+    if index + 1 < nodes.len() && nodes[index].same_flags(&nodes[index + 1]) {
         let next = nodes.remove(index + 1);
 
         nodes[index].join(next)?;
@@ -226,3 +416,97 @@ fn try_join_component_into_surroundings(
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        component::SingleFlagWithData,
+        dom::{MemoryDom, MemoryNode},
+    };
+
+    use super::*;
+
+    fn container(html: &str) -> (MemoryNode, TextContainer<MemoryDom>) {
+        let root = MemoryNode::element("p");
+        let text = MemoryNode::text(html);
+        root.append_child(&text);
+
+        let container = TextContainer::new(text, Rc::new(ComponentRegistry::default())).unwrap();
+
+        (root, container)
+    }
+
+    #[test]
+    fn split_wrap_and_join() {
+        let (root, mut container) = container("Hello World!");
+
+        let first = container.text[0].node.clone();
+        let world = container.split_node(&first, 6).unwrap();
+        container.split_node(&world, 5).unwrap();
+
+        assert_eq!(container.text.len(), 3);
+        assert_eq!(container.text[2].offset, 11);
+
+        container
+            .add_flag_to_node(&world, FlagsWithData::new_flag(ComponentFlag::BOLD))
+            .unwrap();
+
+        assert_eq!(
+            root.inner_html(),
+            "Hello <span class=\"editor-bold\">World</span>!"
+        );
+
+        container
+            .remove_flag_from_node(&world, &FlagsWithData::new_flag(ComponentFlag::BOLD))
+            .unwrap();
+
+        // Unwrapped and joined back together.
+        assert_eq!(container.text.len(), 1);
+        assert_eq!(root.inner_html(), "Hello World!");
+        assert_eq!(root.children().len(), 1);
+    }
+
+    #[test]
+    fn saved_flags_round_trip() {
+        let flags = vec![
+            SavedNodeFlag {
+                offset: 2,
+                length: Some(3),
+                flags: vec![SingleFlagWithData::new(ComponentFlag::HIGHLIGHT, 1)],
+            },
+            SavedNodeFlag {
+                offset: 8,
+                length: None,
+                flags: vec![SingleFlagWithData::new(ComponentFlag::ITALICIZE, 0)],
+            },
+        ];
+
+        let (root, mut container) = container("0123456789");
+        container.apply_saved_flags(&flags).unwrap();
+
+        assert_eq!(container.saved_flags(), flags);
+        assert_eq!(container.text_content(), "0123456789");
+        assert_eq!(root.text_content(), "0123456789");
+        assert_eq!(root.children().len(), 4);
+
+        // Replace them with a single section.
+        let replaced = vec![SavedNodeFlag {
+            offset: 0,
+            length: Some(4),
+            flags: vec![SingleFlagWithData::new(ComponentFlag::UNDERLINE, 0)],
+        }];
+
+        container.set_saved_flags(&replaced).unwrap();
+
+        assert_eq!(container.saved_flags(), replaced);
+        assert_eq!(
+            root.inner_html(),
+            "<span class=\"editor-underline\">0123</span>456789"
+        );
+
+        // Dropping the container unwraps everything.
+        drop(container);
+
+        assert_eq!(root.inner_html(), "0123456789");
+    }
+}
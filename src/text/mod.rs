@@ -1,4 +1,6 @@
+#[cfg(feature = "frontend")]
 use wasm_bindgen::JsCast;
+#[cfg(feature = "frontend")]
 use web_sys::{Node, Text};
 
 mod container;
@@ -7,14 +9,17 @@ mod wrapper;
 pub use container::*;
 pub use wrapper::*;
 
+#[cfg(feature = "frontend")]
 use crate::ComponentFlag;
 
+#[cfg(feature = "frontend")]
 pub struct TextContentWithFlag {
     pub flag: ComponentFlag,
     pub first_text_node: Text,
     pub content: String,
 }
 
+#[cfg(feature = "frontend")]
 /// Returns all text nodes in the `Node`.
 pub fn return_all_text_nodes(container: &Node) -> Vec<Text> {
     let mut found = Vec::new();
@@ -34,6 +39,7 @@ pub fn return_all_text_nodes(container: &Node) -> Vec<Text> {
     found
 }
 
+#[cfg(feature = "frontend")]
 /// Return all text `Node`s which are between the start_node and the end_node parameters.
 pub fn get_all_text_nodes_in_container(
     container: Node,
@@ -1,56 +1,72 @@
-use wasm_bindgen::{JsCast, UnwrapThrowExt};
-use web_sys::{HtmlElement, Text};
+use std::rc::Rc;
 
-use crate::{component::FlagsWithData, ComponentFlag, Result};
+use crate::{
+    component::{ComponentRegistry, FlagsWithData},
+    dom::{DefaultDom, Dom},
+    layer::LayerId,
+    ComponentFlag,
+};
 
 /// Contains the Text Node which can be changed with certain flags.
-#[derive(Debug, Clone)]
-pub struct WrappedText {
+pub struct WrappedText<D: Dom = DefaultDom> {
     /// Span container around the text node.
-    container: HtmlElement,
+    container: D::Element,
 
     // TODO: Private
-    pub node: Text,
+    pub node: D::Text,
 
     pub offset: u32,
 
     pub flag: FlagsWithData,
+
+    /// Flags of the visible annotation layers. Sorted by the layer id.
+    pub(crate) layers: Vec<(LayerId, FlagsWithData)>,
+
+    /// Used to create the class names for the flags.
+    components: Rc<ComponentRegistry>,
 }
 
-impl WrappedText {
-    pub fn wrap(text: Text, offset: u32, flag: FlagsWithData) -> Result<Self> {
+impl<D: Dom> WrappedText<D> {
+    pub fn wrap(
+        text: D::Text,
+        offset: u32,
+        flag: FlagsWithData,
+        components: Rc<ComponentRegistry>,
+    ) -> Result<Self, D::Error> {
         Ok(Self {
-            container: create_container(&text, flag.clone())?,
+            container: create_container::<D>(&text, !flag.is_empty())?,
             node: text,
             offset,
             flag,
-        })
+            layers: Vec::new(),
+            components,
+        }
+        .with_class_name())
     }
 
-    pub fn split(&self, index: u32) -> Result<Self> {
-        let text_split = self.node.split_text(index)?;
+    pub fn split(&self, index: u32) -> Result<Self, D::Error> {
+        let text_split = D::split_text(&self.node, index)?;
         // Move Text Split to outer container layer. It'll we wrapped with container
-        self.container.after_with_node_1(&text_split)?;
+        D::insert_text_after(&self.container, &text_split)?;
 
         Ok(Self {
-            container: create_container(&text_split, self.flag.clone())?,
+            container: create_container::<D>(&text_split, !self.is_plain())?,
             node: text_split,
             flag: self.flag.clone(),
+            layers: self.layers.clone(),
             offset: self.offset + index,
-        })
+            components: self.components.clone(),
+        }
+        .with_class_name())
     }
 
-    pub fn unwrap(&self) -> Result<()> {
+    pub fn unwrap(&self) -> Result<(), D::Error> {
         // TODO: If only the Text is selected (get_selection()) then the outer parent of self.container will be selected for a tick or two.
-        self.container.replace_with_with_node_1(&self.node)?;
-
-        Ok(())
+        D::replace_with_text(&self.container, &self.node)
     }
 
-    pub fn join(&mut self, other: Self) -> Result<()> {
-        if let Some(text) = other.node.text_content() {
-            self.node.append_data(&text)?;
-        }
+    pub fn join(&mut self, other: Self) -> Result<(), D::Error> {
+        D::append_data(&self.node, &D::text_data(&other.node))?;
 
         other.remove();
 
@@ -64,24 +80,80 @@ impl WrappedText {
         new_data_pos: u32,
     ) {
         if self.flag.flag.contains(flag) {
-            for (comp_flag, data) in &mut self.flag.data {
-                if flag == *comp_flag && last_data_pos == *data {
-                    *data = new_data_pos;
-                    break;
-                }
+            if self.flag.data.contains(&(flag, new_data_pos)) {
+                // Already contains the new data. Only remove the old one.
+                self.flag.data.retain(|v| *v != (flag, last_data_pos));
+            } else if let Some(item) = self
+                .flag
+                .data
+                .iter_mut()
+                .find(|v| **v == (flag, last_data_pos))
+            {
+                item.1 = new_data_pos;
             }
+
+            self.flag.data.sort_unstable();
+
+            D::set_class_name(&self.container, &self.class_name());
+        }
+    }
+
+    /// The Element the text is inside of. Ignores our own container.
+    pub fn parent_element(&self) -> Option<D::Element> {
+        if self.is_plain() {
+            D::text_parent(&self.node)
+        } else {
+            D::element_parent(&self.container)
         }
     }
 
+    /// Our span around the text. None if the text isn't wrapped.
+    pub fn wrapper(&self) -> Option<&D::Element> {
+        (!self.is_plain()).then_some(&self.container)
+    }
+
     pub fn remove(&self) {
-        self.container.remove();
-        self.node.remove();
+        D::remove_element(&self.container);
+        D::remove_text(&self.node);
     }
 
     pub fn are_flags_empty(&self) -> bool {
         self.flag.is_empty()
     }
 
+    /// True if neither the listener nor a layer flagged the text.
+    pub(crate) fn is_plain(&self) -> bool {
+        self.flag.is_empty() && self.layers.is_empty()
+    }
+
+    /// True if the text can be joined with the other one.
+    pub(crate) fn same_flags(&self, other: &Self) -> bool {
+        self.flag == other.flag && self.layers == other.layers
+    }
+
+    pub fn layer_flag(&self, layer: LayerId) -> Option<&FlagsWithData> {
+        self.layers
+            .iter()
+            .find(|(id, _)| *id == layer)
+            .map(|(_, flag)| flag)
+    }
+
+    pub fn set_layer_flag(&mut self, layer: LayerId, value: FlagsWithData) -> Result<(), D::Error> {
+        self.layers.retain(|(id, _)| *id != layer);
+
+        if !value.is_empty() {
+            self.layers.push((layer, value));
+            self.layers.sort_by_key(|(id, _)| *id);
+        }
+
+        self.update_container()
+    }
+
+    pub(crate) fn remove_all_layers(&mut self) -> Result<(), D::Error> {
+        self.layers.clear();
+        self.update_container()
+    }
+
     pub fn intersects_flag(&self, value: ComponentFlag) -> bool {
         self.flag.intersects_flag(value)
     }
@@ -90,54 +162,107 @@ impl WrappedText {
         self.flag.contains(value)
     }
 
-    pub fn remove_all_flag(&mut self) -> Result<()> {
+    pub fn remove_all_flag(&mut self) -> Result<(), D::Error> {
         self.flag.clear();
         self.update_container()
     }
 
-    pub fn remove_flag(&mut self, value: &FlagsWithData) -> Result<()> {
+    pub fn remove_flag(&mut self, value: &FlagsWithData) -> Result<(), D::Error> {
         self.flag.remove(value);
         self.update_container()
     }
 
-    pub fn add_flag(&mut self, value: FlagsWithData) -> Result<()> {
+    pub fn add_flag(&mut self, value: FlagsWithData) -> Result<(), D::Error> {
+        // Components which can't overlap replace their data.
+        for flag in value.flag.separate_bits() {
+            if !self.components.get(flag).is_some_and(|v| v.overlapping()) {
+                self.flag.remove(&FlagsWithData::new_flag(flag));
+            }
+        }
+
         self.flag.insert(value);
         self.update_container()
     }
 
-    pub fn set_flag(&mut self, value: FlagsWithData) -> Result<()> {
+    pub fn set_flag(&mut self, value: FlagsWithData) -> Result<(), D::Error> {
         self.flag = value;
         self.update_container()
     }
 
-    fn update_container(&self) -> Result<()> {
-        self.container
-            .set_class_name(&self.flag.generate_class_name());
+    fn class_name(&self) -> String {
+        let mut classes = self.components.generate_class_name(&self.flag);
+
+        if !self.layers.is_empty() {
+            if !classes.is_empty() {
+                classes += " ";
+            }
+
+            classes += "editor-layer";
+        }
+
+        for (layer, _) in &self.layers {
+            classes += " ";
+            classes += &layer.class_name();
+        }
+
+        classes
+    }
+
+    fn with_class_name(self) -> Self {
+        D::set_class_name(&self.container, &self.class_name());
+        self
+    }
+
+    fn update_container(&self) -> Result<(), D::Error> {
+        D::set_class_name(&self.container, &self.class_name());
 
-        if self.flag.is_empty() && self.container.parent_element().is_some() {
+        let is_wrapped = D::element_parent(&self.container).is_some();
+
+        if self.is_plain() && is_wrapped {
             // Unwrap the container.
             self.unwrap()?;
-        } else if !self.flag.is_empty() && self.container.parent_element().is_none() {
+        } else if !self.is_plain() && !is_wrapped {
             // Wrap the Text Node
-            self.node.before_with_node_1(&self.container)?;
-            self.container.append_child(&self.node)?;
+            D::wrap_text(&self.container, &self.node)?;
         }
 
         Ok(())
     }
 }
 
-fn create_container(text_node: &Text, flag: FlagsWithData) -> Result<HtmlElement> {
-    let container = text_node
-        .owner_document()
-        .unwrap_throw()
-        .create_element("span")?;
-    container.set_class_name(&flag.generate_class_name());
+// Derived impls would require `D` itself to implement them.
+impl<D: Dom> Clone for WrappedText<D> {
+    fn clone(&self) -> Self {
+        Self {
+            container: self.container.clone(),
+            node: self.node.clone(),
+            offset: self.offset,
+            flag: self.flag.clone(),
+            layers: self.layers.clone(),
+            components: self.components.clone(),
+        }
+    }
+}
+
+impl<D: Dom> std::fmt::Debug for WrappedText<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WrappedText")
+            .field("container", &self.container)
+            .field("node", &self.node)
+            .field("offset", &self.offset)
+            .field("flag", &self.flag)
+            .field("layers", &self.layers)
+            .field("components", &self.components)
+            .finish()
+    }
+}
+
+fn create_container<D: Dom>(text_node: &D::Text, is_wrapped: bool) -> Result<D::Element, D::Error> {
+    let container = D::create_span(text_node)?;
 
-    if !flag.is_empty() {
-        text_node.before_with_node_1(&container)?;
-        container.append_child(text_node)?;
+    if is_wrapped {
+        D::wrap_text(&container, text_node)?;
     }
 
-    Ok(container.unchecked_into())
+    Ok(container)
 }
@@ -1,14 +1,14 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{borrow::Cow, cell::RefCell, rc::Rc};
 
 use chrono::{Duration, Utc};
 use wasm_bindgen::{prelude::Closure, JsCast, UnwrapThrowExt};
-use web_sys::{HtmlElement, MouseEvent, Selection};
+use web_sys::{Element, HtmlElement, KeyboardEvent, MouseEvent, Selection};
 
 use crate::{
-    component::{Component, Context, Highlight, List, Note},
+    component::DynComponent,
     helper::{parents_contains_element, TargetCast},
-    listener::{ListenerEvent, SharedListenerData},
-    selection,
+    listener::{close_toolbar, notify_changed, SharedListenerData},
+    selection::{self, NodeContainer},
     util::ElementEvent,
     ComponentFlag, ListenerId, Result,
 };
@@ -21,22 +21,54 @@ pub struct Toolbar {
 
     listener_id: ListenerId,
     data: SharedListenerData,
-    func: ListenerEvent,
 
     buttons: Vec<Button>,
     expanded_index: Option<usize>,
+
+    pub(crate) config: ToolbarConfig,
+}
+
+/// Which Components have a button in the toolbar.
+///
+/// By default every Component with `Component::SHOW_BUTTON` has one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolbarConfig {
+    include: ComponentFlag,
+    exclude: ComponentFlag,
+}
+
+impl ToolbarConfig {
+    /// Always show the Components' button.
+    pub fn include(mut self, flag: ComponentFlag) -> Self {
+        self.include.insert(flag);
+        self.exclude.remove(flag);
+        self
+    }
+
+    /// Never show the Components' button.
+    pub fn exclude(mut self, flag: ComponentFlag) -> Self {
+        self.exclude.insert(flag);
+        self.include.remove(flag);
+        self
+    }
+
+    pub fn shows_button(&self, component: &dyn DynComponent) -> bool {
+        let flag = component.flag();
+
+        (component.show_button() || self.include.contains(flag)) && !self.exclude.contains(flag)
+    }
 }
 
 impl Toolbar {
-    pub fn new(listener_id: ListenerId, data: SharedListenerData, func: &ListenerEvent) -> Self {
+    pub fn new(listener_id: ListenerId, data: SharedListenerData) -> Self {
         Self {
             data,
             listener_id,
             popup: None,
             buttons: Vec::new(),
             listeners: Vec::new(),
-            func: func.clone(),
             expanded_index: None,
+            config: ToolbarConfig::default(),
         }
     }
 
@@ -89,6 +121,31 @@ impl Toolbar {
         }
     }
 
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    pub fn contains(&self, element: &Element) -> bool {
+        self.popup
+            .as_ref()
+            .is_some_and(|popup| parents_contains_element(element, popup))
+    }
+
+    /// Focuses the button. Focuses the first option instead if it's expanded.
+    pub fn focus_button(&self, index: usize) -> bool {
+        let Some(button) = self.buttons.get(index) else {
+            return false;
+        };
+
+        let element = button
+            .options
+            .first()
+            .map(|(v, _)| v)
+            .unwrap_or(&button.element);
+
+        element.focus().is_ok()
+    }
+
     fn create_popup(&mut self) -> Result<()> {
         let popup_element: HtmlElement = self
             .listener_id
@@ -122,173 +179,168 @@ impl Toolbar {
         // Create the mouse up listener
         {
             let listener_id = self.listener_id;
-            let func = self.func.clone();
             let data = self.data.clone();
-            let document = self.listener_id.document();
 
             let function = Closure::wrap(Box::new(move |e: MouseEvent| {
-                let click_element: HtmlElement = e.target_unchecked_into();
+                let click_element: Element = e.target_unchecked_into();
                 let is_held = Utc::now().signed_duration_since(*last_clicked.borrow())
                     >= Duration::milliseconds(500);
 
-                // Get the selection
-                if let Some(selection) = document
-                    .get_selection()
-                    .unwrap_throw()
-                    .filter(|v| !v.is_collapsed())
-                {
-                    {
-                        let listener = listener_id.try_get().unwrap();
-                        let mut borrow = listener.borrow_mut();
-
-                        for (idx, button) in borrow.toolbar.buttons.iter().enumerate() {
-                            if parents_contains_element(
-                                click_element.unchecked_ref(),
-                                &button.element,
-                            ) {
-                                match button.type_of {
-                                    ComponentFlag::HIGHLIGHT => {
-                                        if is_held {
-                                            borrow.toolbar.expanded_index = Some(idx);
-                                            drop(borrow);
-                                        } else {
-                                            drop(borrow);
-                                            let context = Context::new(
-                                                Rc::new(RefCell::new(
-                                                    selection::get_nodes_in_selection(
-                                                        selection.clone(),
-                                                        data.clone(),
-                                                    )
-                                                    .unwrap_throw(),
-                                                )),
-                                                document.clone(),
-                                            );
-
-                                            Highlight.on_click_button(&context).unwrap_throw();
-                                        }
-                                    }
-
-                                    ComponentFlag::NOTE => {
-                                        if is_held {
-                                            borrow.toolbar.expanded_index = Some(idx);
-                                            drop(borrow);
-                                        } else {
-                                            drop(borrow);
-                                            let context = Context::new(
-                                                Rc::new(RefCell::new(
-                                                    selection::get_nodes_in_selection(
-                                                        selection.clone(),
-                                                        data.clone(),
-                                                    )
-                                                    .unwrap_throw(),
-                                                )),
-                                                document.clone(),
-                                            );
-
-                                            Note.on_click_button(&context).unwrap_throw();
-                                        }
-                                    }
-
-                                    ComponentFlag::LIST => {
-                                        drop(borrow);
-
-                                        let context = Context::new(
-                                            Rc::new(RefCell::new(
-                                                selection::get_nodes_in_selection(
-                                                    selection.clone(),
-                                                    data.clone(),
-                                                )
-                                                .unwrap_throw(),
-                                            )),
-                                            document.clone(),
-                                        );
-
-                                        List.on_click_button(&context).unwrap_throw();
-                                    }
-
-                                    _ => drop(borrow),
-                                }
-
-                                // Reload the toolbar
-                                {
-                                    let listener = listener_id.try_get().unwrap();
-                                    let mut borrow = listener.borrow_mut();
-
-                                    if let Err(e) = borrow.toolbar.reload(selection) {
-                                        error!("Failed to open toolbar: {e:?}");
-                                    }
-                                }
-
-                                break;
-                            }
+                click_button(listener_id, &data, &click_element, is_held);
+            }) as Box<dyn Fn(MouseEvent)>);
+
+            self.listeners.push(ElementEvent::link(
+                popup_element.clone().unchecked_into(),
+                function,
+                |t, f| t.add_event_listener_with_callback("mouseup", f),
+                Box::new(|t, f| t.remove_event_listener_with_callback("mouseup", f)),
+            ));
+        }
+
+        // Create the key down listener
+        {
+            let listener_id = self.listener_id;
+            let data = self.data.clone();
+
+            let function = Closure::wrap(Box::new(move |e: KeyboardEvent| {
+                let is_held = match e.key().as_str() {
+                    "Enter" | " " => false,
+                    // Expands the button like holding it down does.
+                    "ArrowDown" => true,
+                    "Escape" => {
+                        e.prevent_default();
+
+                        if let Some(listener) = listener_id.try_get() {
+                            close_toolbar(&listener).unwrap_throw();
                         }
+
+                        return;
                     }
+                    _ => return,
+                };
 
-                    (func.borrow_mut())(listener_id);
+                e.prevent_default();
+
+                let target: Element = e.target_unchecked_into();
+
+                // Keep the focus on the button since the toolbar was recreated.
+                if let Some(idx) = click_button(listener_id, &data, &target, is_held) {
+                    if let Some(listener) = listener_id.try_get() {
+                        listener.borrow().toolbar.focus_button(idx);
+                    }
                 }
-            }) as Box<dyn Fn(MouseEvent)>);
+            }) as Box<dyn Fn(KeyboardEvent)>);
 
             self.listeners.push(ElementEvent::link(
                 popup_element.clone().unchecked_into(),
                 function,
-                |t, f| t.add_event_listener_with_callback("mouseup", f),
-                Box::new(|t, f| t.remove_event_listener_with_callback("mouseup", f)),
+                |t, f| t.add_event_listener_with_callback("keydown", f),
+                Box::new(|t, f| t.remove_event_listener_with_callback("keydown", f)),
             ));
         }
 
         self.popup = Some(popup_element);
 
-        let selected = if let Some(selection) = self
+        let nodes = self
             .listener_id
             .document()
             .get_selection()
             .unwrap_throw()
             .filter(|v| !v.is_collapsed())
+            .map(|selection| {
+                Rc::new(RefCell::new(
+                    selection::get_nodes_in_selection(selection, self.data.clone()).unwrap_throw(),
+                ))
+            });
+
+        let selected = nodes
+            .as_ref()
+            .map(|v| v.borrow().get_selected_data_ids())
+            .unwrap_or_default();
+
+        let components = self
+            .data
+            .upgrade()
+            .expect_throw("data upgrade")
+            .borrow()
+            .components
+            .clone();
+
+        let config = self.config.clone();
+
+        for component in components
+            .iter()
+            .filter(|v| config.shows_button(v.as_ref()))
         {
-            let node_sel =
-                selection::get_nodes_in_selection(selection, self.data.clone()).unwrap_throw();
-            node_sel
-                .get_selected_data_ids()
-                .into_iter()
-                .map(|(v, _)| v)
-                .collect()
-        } else {
-            Vec::new()
-        };
-
-        self.create_button::<Highlight>(&selected)?;
-        self.create_button::<Note>(&selected)?;
-        self.create_button::<List>(&selected)?;
+            self.create_button(component.as_ref(), &selected, nodes.as_ref())?;
+        }
 
         Ok(())
     }
 
-    fn create_button<C: Component + 'static>(&mut self, selected: &[ComponentFlag]) -> Result<()> {
-        if let Some(idx) = self.expanded_index {
-            if idx == self.buttons.len() {
-                // TODO: Create the expanded button
-
-                // return Ok(());
+    fn create_button(
+        &mut self,
+        component: &dyn DynComponent,
+        selected: &[(ComponentFlag, u32)],
+        nodes: Option<&Rc<RefCell<NodeContainer>>>,
+    ) -> Result<()> {
+        let document = self.listener_id.document();
+
+        let expanded_options = match (self.expanded_index, nodes) {
+            (Some(idx), Some(nodes)) if idx == self.buttons.len() => {
+                component.expanded_options(nodes.clone(), document.clone())
             }
-        }
+            _ => Vec::new(),
+        };
 
-        let element: HtmlElement = self
-            .listener_id
-            .document()
-            .create_element("div")?
-            .unchecked_into();
+        let element: HtmlElement = document.create_element("div")?.unchecked_into();
 
-        element.set_inner_text(C::TITLE);
         element.set_class_name("editor-button");
+        make_focusable(&element)?;
+
+        let mut options = Vec::new();
+
+        if expanded_options.is_empty() {
+            element.set_inner_text(component.title());
+        } else {
+            element.class_list().add_1("expanded")?;
+
+            for option in expanded_options {
+                let option_element: HtmlElement = document.create_element("div")?.unchecked_into();
+
+                // Style the option like the text it'd create.
+                let class_name = ["editor-option"]
+                    .into_iter()
+                    .chain(component.class_name())
+                    .map(Cow::Borrowed)
+                    .chain(component.data_class(option.data))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                option_element.set_class_name(&class_name);
+                option_element.set_title(&option.title);
+                option_element.set_attribute("aria-label", &option.title)?;
+                make_focusable(&option_element)?;
+
+                if selected.contains(&(component.flag(), option.data)) {
+                    option_element.class_list().add_1("selected")?;
+                }
+
+                element.append_child(&option_element)?;
+
+                options.push((option_element, option.data));
+            }
+        }
 
         self.popup.as_ref().unwrap().append_child(&element)?;
 
         let button = Button {
             element,
-            type_of: C::FLAG,
+            type_of: component.flag(),
+            options,
         };
 
-        button.set_selected(selected.iter().any(|&f| f == button.type_of))?;
+        button.set_selected(selected.iter().any(|v| v.0 == button.type_of))?;
 
         self.buttons.push(button);
 
@@ -296,9 +348,108 @@ impl Toolbar {
     }
 }
 
+fn make_focusable(element: &HtmlElement) -> Result<()> {
+    element.set_tab_index(0);
+    element.set_attribute("role", "button")
+}
+
+/// Activates the button which contains the element. Returns the index of the button.
+fn click_button(
+    listener_id: ListenerId,
+    data: &SharedListenerData,
+    element: &Element,
+    is_held: bool,
+) -> Option<usize> {
+    let document = listener_id.document();
+
+    // Get the selection
+    let selection = document
+        .get_selection()
+        .unwrap_throw()
+        .filter(|v| !v.is_collapsed())?;
+
+    // (index, flag, clicked option, is expanded)
+    let clicked = {
+        let listener = listener_id.try_get().unwrap();
+        let borrow = listener.borrow();
+
+        borrow
+            .toolbar
+            .buttons
+            .iter()
+            .enumerate()
+            .find(|(_, button)| parents_contains_element(element, &button.element))
+            .map(|(idx, button)| {
+                let option = button
+                    .options
+                    .iter()
+                    .find(|(option, _)| parents_contains_element(element, option))
+                    .map(|&(_, data)| data);
+
+                (idx, button.type_of, option, !button.options.is_empty())
+            })
+    };
+
+    let component = clicked.and_then(|(_, flag, _, _)| {
+        data.upgrade()
+            .and_then(|v| v.borrow().components.get(flag).cloned())
+    });
+
+    if let (Some((idx, _, option, is_expanded)), Some(component)) = (clicked, component) {
+        let nodes = Rc::new(RefCell::new(
+            selection::get_nodes_in_selection(selection.clone(), data.clone()).unwrap_throw(),
+        ));
+
+        let expanded_index = if is_expanded {
+            // Clicking the expanded button outside of an option collapses it.
+            if let Some(option) = option {
+                component
+                    .on_click_option(nodes, document.clone(), option)
+                    .unwrap_throw();
+            }
+
+            None
+        } else if is_held
+            && !component
+                .expanded_options(nodes.clone(), document.clone())
+                .is_empty()
+        {
+            Some(idx)
+        } else {
+            component
+                .on_click_button(nodes, document.clone())
+                .unwrap_throw();
+
+            None
+        };
+
+        // Reload the toolbar
+        {
+            let listener = listener_id.try_get().unwrap();
+            let mut borrow = listener.borrow_mut();
+
+            borrow.toolbar.expanded_index = expanded_index;
+
+            if let Err(e) = borrow.toolbar.reload(selection) {
+                error!("Failed to open toolbar: {e:?}");
+            }
+        }
+    }
+
+    if let Some(data) = data.upgrade() {
+        data.borrow_mut().commit();
+    }
+
+    notify_changed(listener_id);
+
+    clicked.map(|(idx, _, _, _)| idx)
+}
+
 pub struct Button {
     type_of: ComponentFlag,
     element: HtmlElement,
+    /// Options of the expanded button with their data id.
+    options: Vec<(HtmlElement, u32)>,
 }
 
 impl Button {
@@ -313,7 +464,8 @@ impl Button {
             class_list.remove_1("selected")?;
         }
 
-        Ok(())
+        self.element
+            .set_attribute("aria-pressed", if value { "true" } else { "false" })
     }
 }
 
@@ -322,3 +474,26 @@ impl Drop for Button {
         self.element.remove();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::component::{Bold, List};
+
+    use super::*;
+
+    #[test]
+    fn config_include_exclude() {
+        let config = ToolbarConfig::default();
+
+        assert!(config.shows_button(&Bold));
+
+        let config = config.exclude(ComponentFlag::BOLD);
+
+        assert!(!config.shows_button(&Bold));
+        assert!(config.shows_button(&List));
+
+        let config = config.include(ComponentFlag::BOLD);
+
+        assert!(config.shows_button(&Bold));
+    }
+}
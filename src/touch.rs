@@ -0,0 +1,283 @@
+//! Pointer Events for touch devices. Long-press selects a word and handles resize the selection.
+
+use std::{cell::RefCell, rc::Rc};
+
+use gloo_timers::callback::Timeout;
+use gloo_utils::window;
+use wasm_bindgen::{prelude::Closure, JsCast, UnwrapThrowExt};
+use web_sys::{Document, Element, Event, HtmlElement, PointerEvent};
+
+use crate::{
+    helper::{parents_contains_class, TargetCast},
+    listener::{display_toolbar, resize_selection, select_from_point, SharedListenerType},
+    segment::Granularity,
+    util::ElementEvent,
+    RangeBox, Result,
+};
+
+/// How long the pointer has to be held down to select a word.
+const LONG_PRESS_MS: u32 = 500;
+
+/// How far the pointer can move before the long-press is cancelled.
+const LONG_PRESS_TOLERANCE: i32 = 10;
+
+#[derive(Default)]
+struct TouchState {
+    long_press: Option<LongPress>,
+    /// If the last long-press selected a word.
+    long_pressed: bool,
+    /// The handle being dragged. True if it's the start handle.
+    dragging: Option<bool>,
+}
+
+struct LongPress {
+    x: i32,
+    y: i32,
+    _timeout: Timeout,
+}
+
+/// Draggable handles at the start and end of the selection.
+struct SelectionHandles {
+    start: HtmlElement,
+    end: HtmlElement,
+}
+
+impl SelectionHandles {
+    fn new(document: &Document) -> Result<Self> {
+        let create = |class_name: &str| -> Result<HtmlElement> {
+            let element: HtmlElement = document.create_element("div")?.unchecked_into();
+
+            element.set_class_name(class_name);
+
+            let style = element.style();
+            style.set_property("position", "absolute")?;
+            style.set_property("display", "none")?;
+            // Don't scroll the page while dragging.
+            style.set_property("touch-action", "none")?;
+
+            document.body().unwrap_throw().append_child(&element)?;
+
+            Ok(element)
+        };
+
+        Ok(Self {
+            start: create("editor-selection-handle start")?,
+            end: create("editor-selection-handle end")?,
+        })
+    }
+
+    /// Returns true if the element is the start handle, false for the end handle.
+    fn handle_of(&self, element: &Element) -> Option<bool> {
+        if element == self.start.unchecked_ref::<Element>() {
+            Some(true)
+        } else if element == self.end.unchecked_ref::<Element>() {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    fn show(&self, range: &RangeBox) -> Result<()> {
+        // The range is relative to the viewport while the handles are positioned in the page.
+        let window = window();
+        let (scroll_x, scroll_y) = (window.scroll_x()?, window.scroll_y()?);
+
+        for (element, point) in [(&self.start, range.start), (&self.end, range.end)] {
+            let style = element.style();
+
+            style.set_property("left", &format!("{}px", point.x + scroll_x))?;
+            style.set_property("top", &format!("{}px", point.y + point.height + scroll_y))?;
+            style.set_property("display", "block")?;
+        }
+
+        Ok(())
+    }
+
+    fn hide(&self) -> Result<()> {
+        self.start.style().set_property("display", "none")?;
+        self.end.style().set_property("display", "none")?;
+
+        Ok(())
+    }
+}
+
+impl Drop for SelectionHandles {
+    fn drop(&mut self) {
+        self.start.remove();
+        self.end.remove();
+    }
+}
+
+pub(crate) fn register_touch_events(
+    listener_rc: &SharedListenerType,
+    listener_class: String,
+) -> Result<()> {
+    let listener_id = listener_rc.borrow().listener_id;
+    let document = listener_id.document();
+
+    let state = Rc::new(RefCell::new(TouchState::default()));
+    let handles = Rc::new(SelectionHandles::new(&document)?);
+
+    // Create the pointer down listener
+    {
+        let state = state.clone();
+        let handles = handles.clone();
+        let listener = Rc::downgrade(listener_rc);
+        let document2 = document.clone();
+
+        let function: Closure<dyn FnMut(PointerEvent)> =
+            Closure::new(move |event: PointerEvent| {
+                if event.pointer_type() != "touch" {
+                    return;
+                }
+
+                let target: Element = event.target_unchecked_into();
+                let mut state_mut = state.borrow_mut();
+
+                state_mut.long_press = None;
+                state_mut.long_pressed = false;
+
+                if let Some(is_start) = handles.handle_of(&target) {
+                    event.prevent_default();
+
+                    state_mut.dragging = Some(is_start);
+                    target
+                        .set_pointer_capture(event.pointer_id())
+                        .unwrap_throw();
+
+                    return;
+                }
+
+                let in_toolbar = listener
+                    .upgrade()
+                    .is_some_and(|v| v.borrow().toolbar.contains(&target));
+
+                if in_toolbar {
+                    return;
+                }
+
+                handles.hide().unwrap_throw();
+
+                if !parents_contains_class(target, &listener_class) {
+                    return;
+                }
+
+                let (x, y) = (event.client_x(), event.client_y());
+
+                let timeout = {
+                    let state = state.clone();
+                    let handles = handles.clone();
+                    let document = document2.clone();
+
+                    Timeout::new(LONG_PRESS_MS, move || {
+                        let range = select_from_point(
+                            listener_id,
+                            x as f32,
+                            y as f32,
+                            Granularity::Word,
+                            &document,
+                        )
+                        .unwrap_throw();
+
+                        if range != RangeBox::default() {
+                            state.borrow_mut().long_pressed = true;
+                            handles.show(&range).unwrap_throw();
+                        }
+                    })
+                };
+
+                state_mut.long_press = Some(LongPress {
+                    x,
+                    y,
+                    _timeout: timeout,
+                });
+            });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.clone().unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback("pointerdown", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("pointerdown", f)),
+        ));
+    }
+
+    // Create the pointer move listener
+    {
+        let state = state.clone();
+        let handles = handles.clone();
+
+        let function: Closure<dyn FnMut(PointerEvent)> =
+            Closure::new(move |event: PointerEvent| {
+                let (x, y) = (event.client_x(), event.client_y());
+                let mut state = state.borrow_mut();
+
+                if let Some(is_start) = state.dragging {
+                    event.prevent_default();
+
+                    let range =
+                        resize_selection(listener_id, is_start, x as f32, y as f32).unwrap_throw();
+
+                    if range != RangeBox::default() {
+                        handles.show(&range).unwrap_throw();
+                    }
+                } else if state.long_press.as_ref().is_some_and(|v| {
+                    (v.x - x).abs() > LONG_PRESS_TOLERANCE || (v.y - y).abs() > LONG_PRESS_TOLERANCE
+                }) {
+                    // We're scrolling.
+                    state.long_press = None;
+                }
+            });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.clone().unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback("pointermove", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("pointermove", f)),
+        ));
+    }
+
+    // Create the pointer up and cancel listeners
+    for event_name in ["pointerup", "pointercancel"] {
+        let state = state.clone();
+        let listener = Rc::downgrade(listener_rc);
+
+        let function: Closure<dyn FnMut(PointerEvent)> =
+            Closure::new(move |_event: PointerEvent| {
+                let was_dragging = {
+                    let mut state = state.borrow_mut();
+
+                    state.long_press = None;
+                    state.dragging.take().is_some()
+                };
+
+                if was_dragging {
+                    display_toolbar(&listener).unwrap_throw();
+                }
+            });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.clone().unchecked_into(),
+            function,
+            move |t, f| t.add_event_listener_with_callback(event_name, f),
+            Box::new(move |t, f| t.remove_event_listener_with_callback(event_name, f)),
+        ));
+    }
+
+    // Don't show the context menu after we've selected a word.
+    {
+        let function: Closure<dyn FnMut(Event)> = Closure::new(move |event: Event| {
+            if state.borrow().long_pressed {
+                event.prevent_default();
+            }
+        });
+
+        listener_rc.borrow_mut().functions.push(ElementEvent::link(
+            document.unchecked_into(),
+            function,
+            |t, f| t.add_event_listener_with_callback("contextmenu", f),
+            Box::new(|t, f| t.remove_event_listener_with_callback("contextmenu", f)),
+        ));
+    }
+
+    Ok(())
+}
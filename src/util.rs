@@ -1,6 +1,6 @@
 use js_sys::Function;
 use wasm_bindgen::{JsCast, JsValue, UnwrapThrowExt};
-use web_sys::EventTarget;
+use web_sys::{EventTarget, Range};
 
 type Destructor = Box<dyn FnOnce(&EventTarget, &Function) -> std::result::Result<(), JsValue>>;
 
@@ -44,12 +44,41 @@ impl Drop for ElementEvent {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct RangeBox {
     pub start: LinePoint,
     pub end: LinePoint,
 }
 
+impl RangeBox {
+    /// Start of the first line and end of the last line inside the range.
+    pub fn from_range(range: &Range) -> Self {
+        let Some(rect_list) = range.get_client_rects() else {
+            return Self::default();
+        };
+
+        let (Some(start), Some(end)) = (
+            rect_list.get(0),
+            rect_list.get(rect_list.length().saturating_sub(1)),
+        ) else {
+            return Self::default();
+        };
+
+        Self {
+            start: LinePoint {
+                x: start.x(),
+                y: start.y(),
+                height: start.height(),
+            },
+            end: LinePoint {
+                x: end.right(),
+                y: end.y(),
+                height: end.height(),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct LinePoint {
     pub x: f64,